@@ -0,0 +1,255 @@
+use alloy::network::{ReceiptResponse, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, TxHash, U256, address, bytes};
+use alloy::providers::{Provider, WalletProvider};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::client::AppProvider;
+use crate::components::{BroadcastedTransaction, Erc20ReturnError, IERC20, PreparedTransfer, RevertReason};
+use crate::token::TokenManager;
+
+/// disperse.app deployment on Ethereum and most major chains
+pub const DISPERSE_ADDRESS: Address = address!("0xD152f549545093347A162Dce210e7293f1452150");
+
+/// Recipients per disperse transaction, to stay well below block gas limits
+pub const DISPERSE_MAX_RECIPIENTS: usize = 200;
+
+/// Creation code of a minimal disperse contract for test chains, with the
+/// same `disperseToken` ABI as disperse.app. For each `i` it calls
+/// `token.transferFrom(msg.sender, recipients[i], values[i])` and reverts
+/// the whole batch if a call fails or returns `false`; empty return data
+/// (USDT) is accepted. Needs no PUSH0, so it also runs on pre-Shanghai chains.
+pub const DISPERSE_BYTECODE: Bytes = bytes!("6100a18061000d6000396000f360003560e01c63c73a2d601461001457600080fd5b3461009c57600435803b1561009c57602435600401604435600401803582358091141561009c576323b872dd60e01b6000523360045260005b8181101561009a578060010160051b8085013560245283013560445260206080606460006000895af11561009c573d156100925760203d1061009c576080511561009c575b60010161004d565b005b600080fd");
+
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract IDisperse {
+        function disperseToken(address token, address[] recipients, uint256[] values) external;
+    }
+}
+
+/// How `batch_payout` sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Through a disperse contract, up to `DISPERSE_MAX_RECIPIENTS` per
+    /// transaction; the wallet must approve it for the batch total first
+    Disperse(Address),
+    /// One `transfer` per recipient, in nonce order
+    Sequential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayoutStatus {
+    Pending,
+    Broadcast,
+    Mined,
+    Reverted,
+    /// Refused before sending (the call would revert or return `false`);
+    /// safe to queue again
+    Failed,
+    /// The send failed in a way that may still have reached the node, e.g.
+    /// a timeout; check the sender's transactions before queueing again
+    Unknown,
+}
+
+/// One recipient of a batch and how far its payment got
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub to: Address,
+    pub amount: U256,
+    pub status: PayoutStatus,
+    /// Shared by every payout of the same disperse transaction
+    pub hash: Option<TxHash>,
+    pub submitted_block: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl Payout {
+    pub fn new(to: Address, amount: U256) -> Self {
+        Self {
+            to,
+            amount,
+            status: PayoutStatus::Pending,
+            hash: None,
+            submitted_block: None,
+            error: None,
+        }
+    }
+
+    fn sent(&mut self, broadcast: &BroadcastedTransaction) {
+        self.status = PayoutStatus::Broadcast;
+        self.hash = Some(broadcast.hash);
+        self.submitted_block = Some(broadcast.submitted_block);
+        self.error = None;
+    }
+
+    fn failed(&mut self, error: &anyhow::Error) {
+        let refused = error.downcast_ref::<RevertReason>().is_some()
+            || error.downcast_ref::<Erc20ReturnError>().is_some();
+
+        self.status = if refused { PayoutStatus::Failed } else { PayoutStatus::Unknown };
+        self.error = Some(error.to_string());
+    }
+}
+
+impl<P: Provider> TokenManager<P> {
+    /// Move broadcast payouts to `Mined` or `Reverted` once their receipts exist
+    pub async fn refresh_batch(&self, payouts: &mut [Payout]) -> Result<()> {
+        let mut hashes: Vec<TxHash> = payouts
+            .iter()
+            .filter(|p| p.status == PayoutStatus::Broadcast)
+            .filter_map(|p| p.hash)
+            .collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        for hash in hashes {
+            let Some(receipt) = self.provider().get_transaction_receipt(hash).await? else {
+                continue;
+            };
+
+            let status = if receipt.status() { PayoutStatus::Mined } else { PayoutStatus::Reverted };
+
+            for payout in payouts.iter_mut().filter(|p| p.hash == Some(hash)) {
+                payout.status = status;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Signing methods: only available with a wallet-backed provider
+impl TokenManager<AppProvider> {
+    /// Gas and fees for sending every pending payout; `calculate_fee` on
+    /// the result gives the batch total
+    pub async fn estimate_batch(&self, payouts: &[Payout], mode: BatchMode) -> Result<PreparedTransfer> {
+        let from = self.provider().default_signer_address();
+
+        let requests: Vec<TransactionRequest> = match mode {
+            BatchMode::Disperse(disperse) => pending(payouts)
+                .chunks(DISPERSE_MAX_RECIPIENTS)
+                .map(|chunk| self.disperse_request(disperse, chunk))
+                .collect(),
+            BatchMode::Sequential => {
+                let token = IERC20::new(self.get_address(), self.provider().clone());
+
+                pending(payouts)
+                    .iter()
+                    .map(|p| token.transfer(p.to, p.amount).into_transaction_request())
+                    .collect()
+            }
+        };
+
+        let mut total: Option<PreparedTransfer> = None;
+        for request in requests {
+            let prepared = self.prepare_request(request.with_from(from)).await?;
+
+            total = Some(match total {
+                Some(mut total) => {
+                    total.gas_estimate += prepared.gas_estimate;
+                    total
+                }
+                None => prepared,
+            });
+        }
+
+        total.ok_or_else(|| anyhow::anyhow!("No pending payouts"))
+    }
+
+    /// Send every `Pending` payout and record its status in place.
+    ///
+    /// Errors only when the batch cannot start; failures of single sends are
+    /// recorded on the payouts they affect.
+    pub async fn batch_payout(&self, payouts: &mut [Payout], mode: BatchMode) -> Result<()> {
+        match mode {
+            BatchMode::Disperse(disperse) => self.disperse(payouts, disperse).await,
+            BatchMode::Sequential => {
+                for payout in payouts.iter_mut().filter(|p| p.status == PayoutStatus::Pending) {
+                    let request = IERC20::new(self.get_address(), self.provider().clone())
+                        .transfer(payout.to, payout.amount)
+                        .into_transaction_request();
+
+                    match self.send_token_call(request).await {
+                        Ok(broadcast) => payout.sent(&broadcast),
+                        Err(e) => {
+                            log::warn!("payout to {} failed: {e}", payout.to);
+                            payout.failed(&e);
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn disperse(&self, payouts: &mut [Payout], disperse: Address) -> Result<()> {
+        let provider = self.provider();
+
+        if provider.get_code_at(disperse).await?.is_empty() {
+            anyhow::bail!("No disperse contract at {disperse}; see deploy_disperse");
+        }
+
+        let total = pending(payouts)
+            .iter()
+            .try_fold(U256::ZERO, |sum, p| sum.checked_add(p.amount))
+            .ok_or_else(|| anyhow::anyhow!("Batch total overflowed"))?;
+
+        let allowance = self.get_allowance(provider.default_signer_address(), disperse).await?;
+        if allowance < total {
+            anyhow::bail!("Disperse allowance {allowance} is below the batch total {total}; approve it first");
+        }
+
+        let indexes: Vec<usize> = (0..payouts.len())
+            .filter(|i| payouts[*i].status == PayoutStatus::Pending)
+            .collect();
+
+        for chunk in indexes.chunks(DISPERSE_MAX_RECIPIENTS) {
+            let batch: Vec<Payout> = chunk.iter().map(|i| payouts[*i].clone()).collect();
+
+            let result = self.send_prepared(self.disperse_request(disperse, &batch)).await;
+
+            for i in chunk.iter() {
+                match &result {
+                    Ok(broadcast) => payouts[*i].sent(broadcast),
+                    Err(e) => payouts[*i].failed(e),
+                }
+            }
+
+            if let Err(e) = result {
+                log::warn!("disperse of {} payouts failed: {e}", chunk.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disperse_request(&self, disperse: Address, payouts: &[Payout]) -> TransactionRequest {
+        let recipients = payouts.iter().map(|p| p.to).collect();
+        let values = payouts.iter().map(|p| p.amount).collect();
+
+        IDisperse::new(disperse, self.provider().clone())
+            .disperseToken(self.get_address(), recipients, values)
+            .into_transaction_request()
+    }
+}
+
+/// Deploy `DISPERSE_BYTECODE` and return its address; meant for test chains
+pub async fn deploy_disperse(provider: &AppProvider) -> Result<Address> {
+    let request = TransactionRequest::default().with_deploy_code(DISPERSE_BYTECODE);
+
+    let receipt = provider.send_transaction(request).await?.get_receipt().await?;
+    receipt.ensure_success()?;
+
+    receipt
+        .contract_address
+        .ok_or_else(|| anyhow::anyhow!("Deployment receipt has no contract address"))
+}
+
+fn pending(payouts: &[Payout]) -> Vec<Payout> {
+    payouts.iter().filter(|p| p.status == PayoutStatus::Pending).cloned().collect()
+}
@@ -6,7 +6,8 @@ use alloy::providers::fillers::{JoinFill, GasFiller, BlobGasFiller, NonceFiller,
 use alloy::providers::{Identity, WsConnect};
 use anyhow::Result;
 use alloy::providers::{ProviderBuilder, RootProvider};
-use alloy::network::EthereumWallet;
+use alloy::network::{EthereumWallet, TxSigner};
+use alloy::primitives::Signature;
 // use alloy::transports::http::{Client, Http};
 use url::Url;
 
@@ -16,6 +17,9 @@ use crate::wallet::Wallet;
 // Type alias for our specific provider stack
 pub type AppProvider = FillProvider<JoinFill<JoinFill<Identity, JoinFill<GasFiller, JoinFill<BlobGasFiller, JoinFill<NonceFiller, ChainIdFiller>>>>, WalletFiller<EthereumWallet>>, RootProvider>;
 
+// Provider without fillers or wallet: can read chain state, cannot sign
+pub type ReadOnlyProvider = RootProvider;
+
 pub struct EvmClient {
     pub provider: Arc<AppProvider>,
     pub address: Address,
@@ -25,18 +29,57 @@ impl EvmClient {
     pub async fn new(config: &Config) -> Result<Self> {
         // Build signer from mnemonic
         let wallet = Wallet::build_signer(&config.phrase, config.password.as_deref(), 0)?;
-        let address = wallet.address();
 
-        let builder = ProviderBuilder::new().wallet(EthereumWallet::from(wallet));
+        Self::with_signer(&config.rpc_url, config.rpc_ws_url.as_deref(), wallet).await
+    }
+
+    /// Connect with any transaction signer, e.g. `signer::ExternalSigner`
+    /// so keys stay out of this process.
+    pub async fn with_signer<S>(rpc_url: &str, rpc_ws_url: Option<&str>, signer: S) -> Result<Self>
+    where
+        S: TxSigner<Signature> + Send + Sync + 'static,
+    {
+        let address = signer.address();
+
+        let builder = ProviderBuilder::new().wallet(EthereumWallet::from(signer));
 
-        let provider = if let Some(ws_url) = &config.rpc_ws_url {
+        let provider = if let Some(ws_url) = rpc_ws_url {
             log::debug!("Start WS connection");
             builder.connect_ws(WsConnect::new(ws_url)).await?
         } else {
             log::debug!("Start HTTP connection");
-            builder.connect_http(Url::parse(&config.rpc_url)?)
+            builder.connect_http(Url::parse(rpc_url)?)
         };
 
         Ok(Self { provider: Arc::new(provider), address })
     }
 }
+
+/// Client for services that must not hold keys (analytics, monitoring).
+///
+/// Works with `TokenManager` read calls and the `monitor` functions;
+/// signing methods such as `broadcast_transfer` do not exist for it.
+pub struct ReadOnlyClient {
+    pub provider: Arc<ReadOnlyProvider>,
+}
+
+impl ReadOnlyClient {
+    pub async fn new(rpc_url: &str, rpc_ws_url: Option<&str>) -> Result<Self> {
+        let builder = ProviderBuilder::new().disable_recommended_fillers();
+
+        let provider = if let Some(ws_url) = rpc_ws_url {
+            log::debug!("Start read-only WS connection");
+            builder.connect_ws(WsConnect::new(ws_url)).await?
+        } else {
+            log::debug!("Start read-only HTTP connection");
+            builder.connect_http(Url::parse(rpc_url)?)
+        };
+
+        Ok(Self { provider: Arc::new(provider) })
+    }
+
+    /// Connect using only the RPC endpoints of `config`; the phrase is ignored.
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        Self::new(&config.rpc_url, config.rpc_ws_url.as_deref()).await
+    }
+}
@@ -1,6 +1,12 @@
+use std::fmt;
+
 use alloy::sol;
-use alloy::primitives::{TxHash, U256};
+use alloy::primitives::{Address, B256, Bytes, FixedBytes, I256, TxHash, U256};
+use alloy::sol_types::{Panic, Revert, SolError, SolInterface};
+use alloy::rpc::types::TransactionReceipt;
+use alloy::transports::TransportError;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 use crate::utils;
 
@@ -10,16 +16,260 @@ sol! {
     #[allow(missing_docs)]
     #[sol(rpc)]
     contract IERC20 {
+        function name() external view returns (string);
+        function symbol() external view returns (string);
+        function decimals() external view returns (uint8);
+        function totalSupply() external view returns (uint256);
         function balanceOf(address account) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
         function transfer(address to, uint256 amount) external returns (bool);
-        function decimals() external view returns (uint8);
+        function approve(address spender, uint256 amount) external returns (bool);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
         event Transfer(address indexed from, address indexed to, uint256 value);
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+    }
+}
+
+// Early tokens (e.g. MKR) return name and symbol as bytes32
+sol! {
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract IERC20Bytes32 {
+        function name() external view returns (bytes32);
+        function symbol() external view returns (bytes32);
+    }
+}
+
+// ERC-6093 errors of OpenZeppelin 5 tokens, plus Pausable, for readable reverts
+sol! {
+    #[derive(Debug)]
+    interface IERC20Errors {
+        error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+        error ERC20InvalidSender(address sender);
+        error ERC20InvalidReceiver(address receiver);
+        error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed);
+        error ERC20InvalidApprover(address approver);
+        error ERC20InvalidSpender(address spender);
+        error EnforcedPause();
+    }
+}
+
+/// Why a call reverted, decoded from its revert data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `require(cond, "...")` or `revert("...")`
+    Error(String),
+    /// Solidity panic code, e.g. 0x11 for arithmetic overflow
+    Panic(U256),
+    /// Custom error; `decoded` is set for the errors in `IERC20Errors`
+    Custom {
+        selector: FixedBytes<4>,
+        decoded: Option<String>,
+        data: Bytes,
+    },
+    /// No revert data; holds whatever message the node gave
+    Unknown(String),
+}
+
+impl RevertReason {
+    pub fn decode(data: &[u8]) -> Self {
+        if let Ok(revert) = Revert::abi_decode(data) {
+            return RevertReason::Error(revert.reason);
+        }
+        if let Ok(panic) = Panic::abi_decode(data) {
+            return RevertReason::Panic(panic.code);
+        }
+        if data.len() < 4 {
+            return RevertReason::Unknown(format!("revert data {}", Bytes::copy_from_slice(data)));
+        }
+
+        let decoded = IERC20Errors::IERC20ErrorsErrors::abi_decode(data).ok().map(|error| {
+            use IERC20Errors::IERC20ErrorsErrors as E;
+
+            match error {
+                E::ERC20InsufficientBalance(e) => format!("{e:?}"),
+                E::ERC20InvalidSender(e) => format!("{e:?}"),
+                E::ERC20InvalidReceiver(e) => format!("{e:?}"),
+                E::ERC20InsufficientAllowance(e) => format!("{e:?}"),
+                E::ERC20InvalidApprover(e) => format!("{e:?}"),
+                E::ERC20InvalidSpender(e) => format!("{e:?}"),
+                E::EnforcedPause(e) => format!("{e:?}"),
+            }
+        });
+
+        RevertReason::Custom {
+            selector: FixedBytes::from_slice(&data[..4]),
+            decoded,
+            data: Bytes::copy_from_slice(data),
+        }
+    }
+
+    /// Revert carried by an `eth_call` / `eth_estimateGas` error, if any
+    pub fn from_rpc_error(error: &TransportError) -> Option<Self> {
+        let payload = error.as_error_resp()?;
+
+        match payload.as_revert_data() {
+            Some(data) if data.is_empty() => Some(RevertReason::Unknown(payload.message.to_string())),
+            Some(data) => Some(Self::decode(&data)),
+            None if payload.message.contains("revert") => Some(RevertReason::Unknown(payload.message.to_string())),
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error(reason) => write!(f, "reverted: {reason}"),
+            RevertReason::Panic(code) => write!(f, "panicked with code {code:#x}"),
+            RevertReason::Custom { decoded: Some(decoded), .. } => write!(f, "reverted with {decoded}"),
+            RevertReason::Custom { selector, data, .. } => write!(f, "reverted with custom error {selector} ({data})"),
+            RevertReason::Unknown(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RevertReason {}
+
+/// A mined transaction that reverted, with the reason recovered by
+/// replaying it at its parent block
+#[derive(Debug, Clone)]
+pub struct TransactionFailure {
+    pub hash: TxHash,
+    pub block_number: Option<u64>,
+    pub gas_used: u64,
+    /// `gas_used * effective_gas_price`, in wei
+    pub fee_paid: U256,
+    /// None when the replay did not revert (state changed within the block)
+    pub reason: Option<RevertReason>,
+}
+
+impl fmt::Display for TransactionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "transaction {} failed", self.hash)?;
+        if let Some(reason) = &self.reason {
+            write!(f, ": {reason}")?;
+        }
+        write!(f, " (gas used {}, fee paid {} wei)", self.gas_used, self.fee_paid)
+    }
+}
+
+impl std::error::Error for TransactionFailure {}
+
+/// Preflight failure: the sender cannot cover a transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsufficientFunds {
+    /// Token balance below the amount sent
+    Token {
+        account: Address,
+        balance: U256,
+        required: U256,
+    },
+    /// Native balance below the maximum fee
+    Gas {
+        account: Address,
+        balance: U256,
+        required: U256,
+    },
+}
+
+impl InsufficientFunds {
+    /// Amount missing, in the token's or the native coin's smallest unit
+    pub fn get_shortfall(&self) -> U256 {
+        match self {
+            InsufficientFunds::Token { balance, required, .. }
+            | InsufficientFunds::Gas { balance, required, .. } => required.saturating_sub(*balance),
+        }
+    }
+}
+
+impl fmt::Display for InsufficientFunds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsufficientFunds::Token { account, balance, required } => write!(
+                f,
+                "insufficient token balance in {account}: have {balance}, need {required}, short {}",
+                self.get_shortfall()
+            ),
+            InsufficientFunds::Gas { account, balance, required } => write!(
+                f,
+                "insufficient gas funds in {account}: have {balance}, need {required}, short {}",
+                self.get_shortfall()
+            ),
+        }
     }
 }
 
+impl std::error::Error for InsufficientFunds {}
+
+/// Outcome of a token call that returns nothing or a `bool`.
+///
+/// Tokens like mainnet USDT return no data; others return `false` instead
+/// of reverting, which would otherwise look like a successful transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Erc20ReturnError {
+    ReturnedFalse,
+    /// Neither empty nor an ABI-encoded bool
+    InvalidReturnData(Bytes),
+}
+
+impl Erc20ReturnError {
+    /// Accept empty return data or `true`
+    pub fn check(data: &[u8]) -> Result<(), Self> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if data == B256::with_last_byte(1).as_slice() {
+            Ok(())
+        } else if data == B256::ZERO.as_slice() {
+            Err(Erc20ReturnError::ReturnedFalse)
+        } else {
+            Err(Erc20ReturnError::InvalidReturnData(Bytes::copy_from_slice(data)))
+        }
+    }
+}
+
+impl fmt::Display for Erc20ReturnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Erc20ReturnError::ReturnedFalse => write!(f, "token call returned false"),
+            Erc20ReturnError::InvalidReturnData(data) => write!(f, "token call returned unexpected data {data}"),
+        }
+    }
+}
+
+impl std::error::Error for Erc20ReturnError {}
+
+/// How a transaction is priced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeMode {
+    /// Type-2 transaction with max fee and priority fee
+    Eip1559,
+    /// Type-0 transaction priced by `eth_gasPrice`
+    Legacy,
+}
+
+/// How the fees of a `PreparedTransfer` were chosen, kept for auditing
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeDetails {
+    /// Name of the `FeeStrategy` pricing path
+    pub strategy: &'static str,
+    /// Base fee the estimate was built on (EIP-1559 only)
+    pub base_fee_per_gas: Option<U256>,
+    /// `eth_feeHistory` reward percentile used for the tip
+    pub reward_percentile: Option<f64>,
+    /// True when a configured cap lowered the estimate
+    pub capped: bool,
+}
+
 pub struct PreparedTransfer {
     pub gas_estimate: u64,
+    pub fee_mode: FeeMode,
+    pub fee_details: FeeDetails,
+    /// Gas price in `FeeMode::Legacy`
     pub max_fee_per_gas: U256,
+    /// Zero in `FeeMode::Legacy`
     pub max_priority_fee_per_gas: U256,
 }
 
@@ -38,6 +288,18 @@ impl PreparedTransfer {
         self.gas_estimate
     }
 
+    pub fn get_fee_mode(&self) -> FeeMode {
+        self.fee_mode
+    }
+
+    /// Legacy gas price; `None` for EIP-1559 transfers
+    pub fn get_gas_price(&self) -> Option<U256> {
+        match self.fee_mode {
+            FeeMode::Legacy => Some(self.max_fee_per_gas),
+            FeeMode::Eip1559 => None,
+        }
+    }
+
     pub fn get_max_fee_per_gas(&self) -> U256 {
         self.max_fee_per_gas
     }
@@ -51,3 +313,84 @@ pub struct BroadcastedTransaction {
     pub hash: TxHash,
     pub submitted_block: u64,
 }
+
+/// Signed transaction not yet sent: persist `raw` before submitting it,
+/// then submit (or rebroadcast) the exact same bytes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTransfer {
+    pub hash: TxHash,
+    /// EIP-2718 encoded signed transaction, as for `eth_sendRawTransaction`
+    pub raw: Bytes,
+    pub from: Address,
+    pub nonce: u64,
+}
+
+/// Requested versus delivered amount of a mined transfer, for
+/// fee-on-transfer and rebasing tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferAmounts {
+    pub requested: U256,
+    /// Sum of the receipt's `Transfer` logs to the recipient
+    pub received: U256,
+    /// Recipient's `balanceOf` change over the block, if the node serves
+    /// historical state; includes other transfers in that block and rebases
+    pub balance_delta: Option<I256>,
+}
+
+impl TransferAmounts {
+    /// Part of the requested amount taken by the token
+    pub fn get_fee(&self) -> U256 {
+        self.requested.saturating_sub(self.received)
+    }
+
+    pub fn is_short(&self) -> bool {
+        self.received < self.requested
+    }
+}
+
+/// How waiting on a broadcast transaction ended
+#[derive(Debug)]
+pub enum ReceiptOutcome {
+    /// Mined successfully with the requested confirmations
+    Confirmed(Box<TransactionReceipt>),
+    /// Its nonce was used by another transaction (speed-up, cancel or a
+    /// send from elsewhere)
+    Replaced { nonce: u64 },
+    /// Gone from the node's mempool with its nonce still unused
+    Dropped,
+    /// Not confirmed within `max_blocks_wait`
+    TimedOut,
+}
+
+/// A broadcast and every replacement sent with the same nonce.
+/// At most one of `hashes` can ever be mined.
+#[derive(Debug, Clone)]
+pub struct ReplacementGroup {
+    pub from: Address,
+    pub nonce: u64,
+    /// Original hash first, most recent replacement last
+    pub hashes: Vec<TxHash>,
+    pub submitted_block: u64,
+}
+
+impl ReplacementGroup {
+    pub fn latest(&self) -> Option<TxHash> {
+        self.hashes.last().copied()
+    }
+}
+
+/// The nonce of a `ReplacementGroup` was used by a transaction that is not
+/// part of the group, so none of its hashes can be mined anymore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceConsumed {
+    pub from: Address,
+    pub nonce: u64,
+}
+
+impl fmt::Display for NonceConsumed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nonce {} of {} was used by a transaction outside this group", self.nonce, self.from)
+    }
+}
+
+impl std::error::Error for NonceConsumed {}
@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use alloy::primitives::Address;
 use zeroize::Zeroizing;
 use std::env;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 #[derive(Debug, Clone)]
@@ -12,6 +13,8 @@ pub struct Config {
     pub password: Option<String>,
     pub usdt_contract: Address,
     pub recipient: Address,
+    /// Extra tokens for `TokenRegistry::from_config`
+    pub token_registry: Option<PathBuf>,
 }
 
 pub struct ConfigOptions {
@@ -21,6 +24,7 @@ pub struct ConfigOptions {
     pub password: Option<String>,
     pub usdt_contract: Address,
     pub recipient: Address,
+    pub token_registry: Option<PathBuf>,
 }
 
 impl Config {
@@ -34,6 +38,7 @@ impl Config {
             password: env::var("PLATFORM_BANK_GENESIS_ADDRESS_PASSPHRASE_PASSWORD").ok(),
             usdt_contract: Address::from_str(&env::var("USDT_CONTRACT_BSC").context("USDT_CONTRACT_BSC not set")?)?,
             recipient: Address::from_str(&env::var("PLATFORM_BSC_ADDRESS_OF_XBTS").context("XBTS_BSC_WALLET not set")?)?,
+            token_registry: env::var("TOKEN_REGISTRY").ok().map(PathBuf::from),
         })
     }
 }
@@ -47,6 +52,7 @@ impl From<ConfigOptions> for Config {
             password: c.password,
             usdt_contract: c.usdt_contract,
             recipient: c.recipient,
+            token_registry: c.token_registry,
         }
     }
 }
@@ -0,0 +1,181 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::components::{FeeDetails, FeeMode};
+
+/// Fee values chosen by a `FeeStrategy`
+pub struct FeeEstimate {
+    /// Gas price in `FeeMode::Legacy`
+    pub max_fee_per_gas: U256,
+    /// Zero in `FeeMode::Legacy`
+    pub max_priority_fee_per_gas: U256,
+    pub details: FeeDetails,
+}
+
+/// Pluggable fee pricing used by `TokenManager::prepare_transfer`.
+#[async_trait]
+pub trait FeeStrategy<P: Provider>: Send + Sync {
+    async fn estimate(&self, provider: &P, mode: FeeMode) -> Result<FeeEstimate>;
+}
+
+/// Presets mapping to `eth_feeHistory` reward percentiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeSpeed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl FeeSpeed {
+    pub fn reward_percentile(self) -> f64 {
+        match self {
+            FeeSpeed::Slow => 10.0,
+            FeeSpeed::Normal => 50.0,
+            FeeSpeed::Fast => 90.0,
+        }
+    }
+}
+
+/// Default strategy: priority fee is the median, over recent blocks, of the
+/// chosen reward percentile; max fee is `next_base_fee * multiplier + tip`.
+/// Legacy chains use `eth_gasPrice`. Caps bound the result.
+#[derive(Debug, Clone)]
+pub struct FeeHistoryStrategy {
+    pub speed: FeeSpeed,
+    /// Blocks of history to sample (1..=1024)
+    pub block_count: u64,
+    pub base_fee_multiplier: u64,
+    /// Floor for the priority fee, for chains whose blocks are mostly empty
+    pub min_priority_fee: Option<U256>,
+    pub max_priority_fee_cap: Option<U256>,
+    /// Upper bound for max fee (or gas price in legacy mode)
+    pub max_fee_cap: Option<U256>,
+}
+
+impl Default for FeeHistoryStrategy {
+    fn default() -> Self {
+        Self {
+            speed: FeeSpeed::Normal,
+            block_count: 10,
+            base_fee_multiplier: 2,
+            min_priority_fee: None,
+            max_priority_fee_cap: None,
+            max_fee_cap: None,
+        }
+    }
+}
+
+impl FeeHistoryStrategy {
+    pub fn new(speed: FeeSpeed) -> Self {
+        Self { speed, ..Self::default() }
+    }
+
+    pub fn with_caps(mut self, max_fee_cap: Option<U256>, max_priority_fee_cap: Option<U256>) -> Self {
+        self.max_fee_cap = max_fee_cap;
+        self.max_priority_fee_cap = max_priority_fee_cap;
+        self
+    }
+
+    pub fn with_min_priority_fee(mut self, min_priority_fee: U256) -> Self {
+        self.min_priority_fee = Some(min_priority_fee);
+        self
+    }
+
+    async fn estimate_eip1559<P: Provider>(&self, provider: &P) -> Result<FeeEstimate> {
+        let percentile = self.speed.reward_percentile();
+
+        let history = provider
+            .get_fee_history(self.block_count, BlockNumberOrTag::Latest, &[percentile])
+            .await?;
+
+        let base_fee = history
+            .next_block_base_fee()
+            .ok_or_else(|| anyhow::anyhow!("Chain does not support EIP-1559"))?;
+
+        let mut rewards: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        rewards.sort_unstable();
+
+        let mut priority_fee = U256::from(rewards.get(rewards.len() / 2).copied().unwrap_or_default());
+        if let Some(min) = self.min_priority_fee {
+            priority_fee = priority_fee.max(min);
+        }
+
+        let mut capped = false;
+        if let Some(cap) = self.max_priority_fee_cap
+            && priority_fee > cap
+        {
+            priority_fee = cap;
+            capped = true;
+        }
+
+        let base_fee = U256::from(base_fee);
+        let mut max_fee_per_gas = base_fee
+            .checked_mul(U256::from(self.base_fee_multiplier))
+            .and_then(|fee| fee.checked_add(priority_fee))
+            .ok_or_else(|| anyhow::anyhow!("max_fee_per_gas overflowed"))?;
+
+        if let Some(cap) = self.max_fee_cap
+            && max_fee_per_gas > cap
+        {
+            max_fee_per_gas = cap;
+            priority_fee = priority_fee.min(cap);
+            capped = true;
+        }
+
+        if capped {
+            log::warn!("fee capped: max_fee {max_fee_per_gas}, priority {priority_fee}, base {base_fee}");
+        }
+
+        Ok(FeeEstimate {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: priority_fee,
+            details: FeeDetails {
+                strategy: "fee_history",
+                base_fee_per_gas: Some(base_fee),
+                reward_percentile: Some(percentile),
+                capped,
+            },
+        })
+    }
+
+    async fn estimate_legacy<P: Provider>(&self, provider: &P) -> Result<FeeEstimate> {
+        let mut gas_price = U256::from(provider.get_gas_price().await?);
+
+        let mut capped = false;
+        if let Some(cap) = self.max_fee_cap
+            && gas_price > cap
+        {
+            gas_price = cap;
+            capped = true;
+        }
+
+        Ok(FeeEstimate {
+            max_fee_per_gas: gas_price,
+            max_priority_fee_per_gas: U256::ZERO,
+            details: FeeDetails {
+                strategy: "gas_price",
+                base_fee_per_gas: None,
+                reward_percentile: None,
+                capped,
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl<P: Provider> FeeStrategy<P> for FeeHistoryStrategy {
+    async fn estimate(&self, provider: &P, mode: FeeMode) -> Result<FeeEstimate> {
+        match mode {
+            FeeMode::Eip1559 => self.estimate_eip1559(provider).await,
+            FeeMode::Legacy => self.estimate_legacy(provider).await,
+        }
+    }
+}
@@ -1,7 +1,16 @@
+pub mod batch;
 pub mod client;
 pub mod components;
 pub mod config;
+pub mod fees;
 pub mod monitor;
+pub mod multicall;
+pub mod nonce;
+pub mod offline;
+pub mod permit;
+pub mod registry;
+pub mod signer;
 pub mod token;
 pub mod utils;
 pub mod wallet;
+pub mod withdrawal;
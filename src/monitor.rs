@@ -9,7 +9,6 @@ use tokio::sync::oneshot;
 use tokio::sync::mpsc;
 use tokio::select;
 
-use crate::client::AppProvider;
 use crate::utils::to_human;
 
 // Re-declare event for decoding
@@ -28,8 +27,8 @@ pub struct IncomingTransfer {
     pub block_timestamp: Option<u64>,
 }
 
-pub async fn monitor(
-    provider: &AppProvider,
+pub async fn monitor<P: Provider>(
+    provider: &P,
     contract_addr: Address,
     destination_wallet: Address,
     decimals: u8,
@@ -82,7 +81,7 @@ pub async fn monitor(
                                         "🚨 Incoming transfer from From: {:?} | Amount: {} USDT | Amount raw: {} USDT",
                                         event.from,
                                         readable,
-                                        event.value.to_string()
+                                        event.value
                                     );
 
                                     match tx.send(IncomingTransfer {
@@ -92,8 +91,8 @@ pub async fn monitor(
                                         from: event.from,
                                         to: event.to,
                                         amount: event.value,
-                                        removed: removed,
-                                        block_timestamp: block_timestamp,
+                                        removed,
+                                        block_timestamp,
                                     }).await {
                                         Ok(_) => {
                                             log::debug!("Blockchain transfer data is sent");
@@ -124,8 +123,8 @@ pub async fn monitor(
     Ok(())
 }
 
-pub async fn monitor_ws(
-    provider: &AppProvider,
+pub async fn monitor_ws<P: Provider>(
+    provider: &P,
     contract_addr: Address,
     destination_wallet: Address,
     decimals: u8,
@@ -175,7 +174,7 @@ pub async fn monitor_ws(
                                     "🚨 Incoming transfer from From: {:?} | Amount: {} USDT | Amount raw: {} USDT",
                                     event.from,
                                     readable,
-                                    event.value.to_string()
+                                    event.value
                                 );
 
                                 match tx.send(IncomingTransfer {
@@ -185,8 +184,8 @@ pub async fn monitor_ws(
                                     from: event.from,
                                     to: event.to,
                                     amount: event.value,
-                                    removed: removed,
-                                    block_timestamp: block_timestamp,
+                                    removed,
+                                    block_timestamp,
                                 }).await {
                                     Ok(_) => {
                                         log::debug!("Blockchain transfer data is sent");
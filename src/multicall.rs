@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use alloy::eips::BlockId;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::bindings::IMulticall3::{Call3, aggregate3Call, getEthBalanceCall};
+use alloy::providers::{MULTICALL3_ADDRESS, Provider};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolCall;
+use anyhow::Result;
+
+use crate::components::IERC20::balanceOfCall;
+use crate::token::TokenManager;
+
+/// Calls per `aggregate3`, to stay below RPC `eth_call` gas limits
+pub const MULTICALL_CHUNK_SIZE: usize = 500;
+
+impl<P: Provider> TokenManager<P> {
+    /// Token balances of many addresses via Multicall3, a few RPC calls in
+    /// total. Addresses whose call fails are logged and left out.
+    pub async fn get_balances_raw(&self, addresses: &[Address]) -> Result<HashMap<Address, U256>> {
+        self.get_balances_raw_at(addresses, BlockId::latest()).await
+    }
+
+    /// `get_balances_raw` at one past block, so all balances are consistent
+    pub async fn get_balances_raw_at(&self, addresses: &[Address], block: BlockId) -> Result<HashMap<Address, U256>> {
+        let token = self.get_address();
+
+        let call = |account| Call3 {
+            target: token,
+            allowFailure: true,
+            callData: balanceOfCall { account }.abi_encode().into(),
+        };
+
+        self.aggregate_balances(addresses, block, call, balanceOfCall::abi_decode_returns).await
+    }
+
+    /// Native balances of many addresses via Multicall3 `getEthBalance`
+    pub async fn get_chain_balances_raw(&self, addresses: &[Address]) -> Result<HashMap<Address, U256>> {
+        self.get_chain_balances_raw_at(addresses, BlockId::latest()).await
+    }
+
+    pub async fn get_chain_balances_raw_at(&self, addresses: &[Address], block: BlockId) -> Result<HashMap<Address, U256>> {
+        let call = |addr| Call3 {
+            target: MULTICALL3_ADDRESS,
+            allowFailure: true,
+            callData: getEthBalanceCall { addr }.abi_encode().into(),
+        };
+
+        self.aggregate_balances(addresses, block, call, getEthBalanceCall::abi_decode_returns).await
+    }
+
+    async fn aggregate_balances(
+        &self,
+        addresses: &[Address],
+        block: BlockId,
+        call: impl Fn(Address) -> Call3,
+        decode: impl Fn(&[u8]) -> alloy::sol_types::Result<U256>,
+    ) -> Result<HashMap<Address, U256>> {
+        let mut balances = HashMap::with_capacity(addresses.len());
+
+        for chunk in addresses.chunks(MULTICALL_CHUNK_SIZE) {
+            let calls: Vec<Call3> = chunk.iter().map(|address| call(*address)).collect();
+
+            let results = match self.aggregate3(calls.clone(), block).await {
+                Ok(results) => results,
+                Err(e) => {
+                    // E.g. no Multicall3 on this chain: one call per address
+                    log::warn!("aggregate3 of {} calls failed, querying one by one: {e}", chunk.len());
+                    self.call_each(calls, block).await
+                }
+            };
+
+            for (address, result) in chunk.iter().zip(results) {
+                match result.map(|data| decode(&data)) {
+                    Some(Ok(balance)) => {
+                        balances.insert(*address, balance);
+                    }
+                    _ => log::warn!("balance of {address} could not be read"),
+                }
+            }
+        }
+
+        Ok(balances)
+    }
+
+    // Return data per call; None where the call failed
+    async fn aggregate3(&self, calls: Vec<Call3>, block: BlockId) -> Result<Vec<Option<Bytes>>> {
+        let request = TransactionRequest::default()
+            .with_to(MULTICALL3_ADDRESS)
+            .with_input(aggregate3Call { calls }.abi_encode());
+
+        let data = self.provider().call(request).block(block).await?;
+        let results = aggregate3Call::abi_decode_returns(&data)?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.success.then_some(result.returnData))
+            .collect())
+    }
+
+    async fn call_each(&self, calls: Vec<Call3>, block: BlockId) -> Vec<Option<Bytes>> {
+        let mut results = Vec::with_capacity(calls.len());
+
+        for call in calls {
+            // getEthBalance targets Multicall3 itself, which may be missing here
+            let request = match getEthBalanceCall::abi_decode(&call.callData) {
+                Ok(native) => {
+                    let balance = self.provider().get_balance(native.addr).block_id(block).await;
+                    results.push(balance.ok().map(|b| Bytes::from(b.to_be_bytes::<32>())));
+                    continue;
+                }
+                Err(_) => TransactionRequest::default().with_to(call.target).with_input(call.callData),
+            };
+
+            results.push(self.provider().call(request).block(block).await.ok());
+        }
+
+        results
+    }
+}
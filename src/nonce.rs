@@ -0,0 +1,105 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+/// Allocation state of one sender
+#[derive(Debug)]
+struct NonceState {
+    next: u64,
+    /// Allocated but never broadcast; handed out again before `next`
+    released: BTreeSet<u64>,
+}
+
+/// Hands out nonces locally so concurrent broadcasts from one sender never
+/// collide. State lives in memory only: the first allocation for a sender
+/// starts at the node's pending count, so after a restart nonces that were
+/// allocated but never sent are reused rather than left as a gap.
+pub struct NonceManager {
+    senders: Mutex<HashMap<Address, NonceState>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self { senders: Mutex::new(HashMap::new()) }
+    }
+
+    /// Next nonce for `sender`
+    pub async fn allocate<P: Provider>(&self, provider: &P, sender: Address) -> Result<u64> {
+        let mut senders = self.senders.lock().await;
+
+        let state = match senders.entry(sender) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let next = provider.get_transaction_count(sender).pending().await?;
+
+                entry.insert(NonceState { next, released: BTreeSet::new() })
+            }
+        };
+
+        let nonce = match state.released.pop_first() {
+            Some(nonce) => nonce,
+            None => {
+                state.next += 1;
+                state.next - 1
+            }
+        };
+
+        log::debug!("nonce {nonce} allocated for {sender}");
+
+        Ok(nonce)
+    }
+
+    /// Give back a nonce whose transaction never reached the node, so the
+    /// next allocation fills the gap instead of stalling later nonces
+    pub async fn release(&self, sender: Address, nonce: u64) -> Result<()> {
+        let mut senders = self.senders.lock().await;
+
+        if let Some(state) = senders.get_mut(&sender) {
+            if nonce + 1 == state.next {
+                state.next = nonce;
+                // Released nonces directly below fold into `next` as well
+                while state.next > 0 && state.released.remove(&(state.next - 1)) {
+                    state.next -= 1;
+                }
+            } else if nonce < state.next {
+                state.released.insert(nonce);
+            }
+
+            log::debug!("nonce {nonce} released for {sender}");
+        }
+
+        Ok(())
+    }
+
+    /// Drop local state for `sender` and take the node's pending count,
+    /// e.g. after "nonce too low" or a transaction sent outside this manager
+    pub async fn resync<P: Provider>(&self, provider: &P, sender: Address) -> Result<u64> {
+        let next = provider.get_transaction_count(sender).pending().await?;
+
+        let mut senders = self.senders.lock().await;
+        senders.insert(sender, NonceState { next, released: BTreeSet::new() });
+
+        log::info!("nonce for {sender} resynced to {next}");
+
+        Ok(next)
+    }
+}
+
+impl Default for NonceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Node errors meaning our local nonce view is stale
+pub fn is_nonce_error(error: &anyhow::Error) -> bool {
+    let message = error.to_string().to_lowercase();
+
+    ["nonce too low", "nonce too high", "already known", "replacement transaction underpriced"]
+        .iter()
+        .any(|needle| message.contains(needle))
+}
@@ -0,0 +1,239 @@
+use alloy::consensus::{SignableTransaction, Transaction, TxEip1559, TxEnvelope, TxLegacy};
+use alloy::eips::eip2718::{Decodable2718, Encodable2718};
+use alloy::network::TxSignerSync;
+use alloy::primitives::{Address, Bytes, TxKind, U256};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::SolCall;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{FeeMode, IERC20, SignedTransfer};
+
+/// Everything needed to sign a transfer without a provider.
+///
+/// Produced online by `TokenManager::export_transfer`, signed on the
+/// air-gapped machine with `sign_offline`, and brought back as a
+/// `SignedTransfer` for `TokenManager::broadcast_signed`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsignedTransfer {
+    pub chain_id: u64,
+    /// ERC-20 contract the transaction calls
+    pub token: Address,
+    /// Account expected to sign
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub fee_mode: FeeMode,
+    /// Gas price in `FeeMode::Legacy`
+    pub max_fee_per_gas: u128,
+    /// Ignored in `FeeMode::Legacy`
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl UnsignedTransfer {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn input(&self) -> Bytes {
+        IERC20::transferCall { to: self.to, amount: self.amount }.abi_encode().into()
+    }
+}
+
+impl SignedTransfer {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Sign `unsigned` with a local key, e.g. from `Wallet::build_signer`.
+/// Makes no network calls.
+pub fn sign_offline(unsigned: &UnsignedTransfer, signer: &PrivateKeySigner) -> Result<SignedTransfer> {
+    if signer.address() != unsigned.from {
+        anyhow::bail!("Signer {} does not match sender {}", signer.address(), unsigned.from);
+    }
+
+    let envelope: TxEnvelope = match unsigned.fee_mode {
+        FeeMode::Eip1559 => {
+            let mut tx = TxEip1559 {
+                chain_id: unsigned.chain_id,
+                nonce: unsigned.nonce,
+                gas_limit: unsigned.gas_limit,
+                max_fee_per_gas: unsigned.max_fee_per_gas,
+                max_priority_fee_per_gas: unsigned.max_priority_fee_per_gas,
+                to: TxKind::Call(unsigned.token),
+                value: U256::ZERO,
+                access_list: Default::default(),
+                input: unsigned.input(),
+            };
+            let signature = signer.sign_transaction_sync(&mut tx)?;
+            tx.into_signed(signature).into()
+        }
+        FeeMode::Legacy => {
+            let mut tx = TxLegacy {
+                chain_id: Some(unsigned.chain_id),
+                nonce: unsigned.nonce,
+                gas_price: unsigned.max_fee_per_gas,
+                gas_limit: unsigned.gas_limit,
+                to: TxKind::Call(unsigned.token),
+                value: U256::ZERO,
+                input: unsigned.input(),
+            };
+            let signature = signer.sign_transaction_sync(&mut tx)?;
+            tx.into_signed(signature).into()
+        }
+    };
+
+    Ok(SignedTransfer {
+        hash: *envelope.tx_hash(),
+        raw: envelope.encoded_2718().into(),
+        from: unsigned.from,
+        nonce: unsigned.nonce,
+    })
+}
+
+/// Decode `signed.raw` and check it is exactly the transfer that was
+/// exported as `unsigned`: sender, chain, token, recipient, amount, nonce,
+/// gas and fees. Run on import, before anything is broadcast.
+pub fn verify_signed(signed: &SignedTransfer, unsigned: &UnsignedTransfer) -> Result<TxEnvelope> {
+    let envelope = TxEnvelope::decode_2718(&mut signed.raw.as_ref())
+        .map_err(|e| anyhow::anyhow!("Invalid raw transaction: {e}"))?;
+
+    if *envelope.tx_hash() != signed.hash {
+        anyhow::bail!("Raw transaction hash {} does not match {}", envelope.tx_hash(), signed.hash);
+    }
+
+    let signer = envelope.signature().recover_address_from_prehash(&envelope.signature_hash())?;
+    if signer != unsigned.from || signed.from != unsigned.from {
+        anyhow::bail!("Transaction signed by {signer}, expected {}", unsigned.from);
+    }
+
+    if signed.nonce != unsigned.nonce {
+        anyhow::bail!("Signed transfer claims nonce {}, expected {}", signed.nonce, unsigned.nonce);
+    }
+
+    let fee_mode_matches = match unsigned.fee_mode {
+        FeeMode::Eip1559 => envelope.is_eip1559(),
+        FeeMode::Legacy => envelope.is_legacy(),
+    };
+    if !fee_mode_matches {
+        anyhow::bail!("Transaction type {} does not match {:?}", envelope.tx_type(), unsigned.fee_mode);
+    }
+
+    check_field("chain id", envelope.chain_id(), Some(unsigned.chain_id))?;
+    check_field("target", envelope.kind(), TxKind::Call(unsigned.token))?;
+    check_field("value", envelope.value(), U256::ZERO)?;
+    check_field("nonce", envelope.nonce(), unsigned.nonce)?;
+    check_field("gas limit", envelope.gas_limit(), unsigned.gas_limit)?;
+    check_field("max fee per gas", envelope.max_fee_per_gas(), unsigned.max_fee_per_gas)?;
+    if unsigned.fee_mode == FeeMode::Eip1559 {
+        check_field(
+            "max priority fee per gas",
+            envelope.max_priority_fee_per_gas(),
+            Some(unsigned.max_priority_fee_per_gas),
+        )?;
+    }
+
+    // Recipient and amount
+    if *envelope.input() != unsigned.input() {
+        let decoded = IERC20::transferCall::abi_decode(envelope.input())
+            .map(|call| format!("transfer of {} to {}", call.amount, call.to))
+            .unwrap_or_else(|_| "not a transfer".to_string());
+        anyhow::bail!(
+            "Calldata is {decoded}, expected transfer of {} to {}",
+            unsigned.amount,
+            unsigned.to
+        );
+    }
+
+    Ok(envelope)
+}
+
+fn check_field<T: PartialEq + std::fmt::Debug>(name: &str, actual: T, expected: T) -> Result<()> {
+    if actual != expected {
+        anyhow::bail!("Signed {name} is {actual:?}, expected {expected:?}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Change = (&'static str, fn(&mut UnsignedTransfer));
+
+    fn unsigned(from: Address, fee_mode: FeeMode) -> UnsignedTransfer {
+        UnsignedTransfer {
+            chain_id: 56,
+            token: Address::repeat_byte(0x55),
+            from,
+            to: Address::repeat_byte(0x11),
+            amount: U256::from(2_500_000u64),
+            nonce: 9,
+            gas_limit: 60_000,
+            fee_mode,
+            max_fee_per_gas: 3_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+        }
+    }
+
+    #[test]
+    fn verifies_the_exported_transfer() {
+        let signer = PrivateKeySigner::random();
+
+        for fee_mode in [FeeMode::Eip1559, FeeMode::Legacy] {
+            let unsigned = unsigned(signer.address(), fee_mode);
+            let signed = sign_offline(&unsigned, &signer).unwrap();
+
+            let json = signed.to_json().unwrap();
+            assert!(verify_signed(&SignedTransfer::from_json(&json).unwrap(), &unsigned).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_a_different_transfer() {
+        let signer = PrivateKeySigner::random();
+        let exported = unsigned(signer.address(), FeeMode::Eip1559);
+
+        let changes: [Change; 8] = [
+            ("recipient", |u| u.to = Address::repeat_byte(0x66)),
+            ("amount", |u| u.amount += U256::from(1u64)),
+            ("token", |u| u.token = Address::repeat_byte(0x77)),
+            ("nonce", |u| u.nonce += 1),
+            ("chain", |u| u.chain_id = 1),
+            ("gas limit", |u| u.gas_limit *= 10),
+            ("max fee", |u| u.max_fee_per_gas *= 10),
+            ("fee mode", |u| u.fee_mode = FeeMode::Legacy),
+        ];
+
+        for (name, change) in changes {
+            let mut tampered = exported.clone();
+            change(&mut tampered);
+
+            let signed = sign_offline(&tampered, &signer).unwrap();
+            assert!(verify_signed(&signed, &exported).is_err(), "{name} change accepted");
+        }
+    }
+
+    #[test]
+    fn rejects_another_signer() {
+        let signer = PrivateKeySigner::random();
+        let other = PrivateKeySigner::random();
+        let exported = unsigned(signer.address(), FeeMode::Eip1559);
+
+        let mut signed = sign_offline(&unsigned(other.address(), FeeMode::Eip1559), &other).unwrap();
+        signed.from = signer.address();
+
+        assert!(verify_signed(&signed, &exported).is_err());
+    }
+}
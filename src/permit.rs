@@ -0,0 +1,251 @@
+use std::borrow::Cow;
+
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, address};
+use alloy::providers::{Provider, WalletProvider};
+use alloy::signers::Signer;
+use alloy::sol;
+use alloy::sol_types::Eip712Domain;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::client::AppProvider;
+use crate::components::BroadcastedTransaction;
+use crate::token::TokenManager;
+use crate::wallet::message;
+
+/// Canonical Permit2 deployment, same address on every chain
+pub const PERMIT2_ADDRESS: Address = address!("0x000000000022D473030F116dDEE9F6B43aC78BA3");
+
+sol! {
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    contract IERC20Permit {
+        function nonces(address owner) external view returns (uint256);
+        function DOMAIN_SEPARATOR() external view returns (bytes32);
+        function version() external view returns (string);
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+    }
+}
+
+// EIP-712 types: names and field order are part of the signed hash
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Serialize)]
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Serialize)]
+    struct TokenPermissions {
+        address token;
+        uint256 amount;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Serialize)]
+    struct PermitTransferFrom {
+        TokenPermissions permitted;
+        address spender;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    #[allow(missing_docs)]
+    struct SignatureTransferDetails {
+        address to;
+        uint256 requestedAmount;
+    }
+
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    contract IPermit2 {
+        function permitTransferFrom(PermitTransferFrom permit, SignatureTransferDetails transferDetails, address owner, bytes signature) external;
+    }
+}
+
+/// Signed EIP-2612 approval; anyone can submit it with `submit_permit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedPermit {
+    pub owner: Address,
+    pub spender: Address,
+    pub value: U256,
+    pub nonce: U256,
+    /// UNIX seconds
+    pub deadline: U256,
+    pub signature: Signature,
+}
+
+/// Signed Permit2 `SignatureTransfer`; only `spender` can redeem it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedPermit2Transfer {
+    pub owner: Address,
+    pub token: Address,
+    pub spender: Address,
+    pub amount: U256,
+    /// Unordered: any unused value works, e.g. random
+    pub nonce: U256,
+    pub deadline: U256,
+    pub signature: Signature,
+}
+
+// Tried after the token's own `version()`; covers OpenZeppelin and USDC
+const PERMIT_VERSIONS: [&str; 2] = ["1", "2"];
+
+impl<P: Provider> TokenManager<P> {
+    /// EIP-712 domain of the token's `permit`, checked against its
+    /// on-chain `DOMAIN_SEPARATOR`
+    pub async fn permit_domain(&self) -> Result<Eip712Domain> {
+        let token = IERC20Permit::new(self.get_address(), self.provider().clone());
+
+        let separator = token
+            .DOMAIN_SEPARATOR()
+            .call()
+            .await
+            .map_err(|e| anyhow::anyhow!("Token does not support EIP-2612 permit: {e}"))?;
+        let chain_id = self.provider().get_chain_id().await?;
+
+        let mut versions: Vec<String> = PERMIT_VERSIONS.iter().map(|v| v.to_string()).collect();
+        if let Ok(version) = token.version().call().await {
+            versions.insert(0, version);
+        }
+
+        for version in versions {
+            let domain = Eip712Domain::new(
+                Some(Cow::Owned(self.get_name().to_string())),
+                Some(Cow::Owned(version)),
+                Some(U256::from(chain_id)),
+                Some(self.get_address()),
+                None,
+            );
+
+            if domain.separator() == separator {
+                return Ok(domain);
+            }
+        }
+
+        anyhow::bail!("Could not reproduce DOMAIN_SEPARATOR {separator} of {}", self.get_address())
+    }
+
+    /// Sign an EIP-2612 approval of `value` for `spender` as `signer`
+    pub async fn sign_permit<S: Signer + Sync>(
+        &self,
+        signer: &S,
+        spender: Address,
+        value: U256,
+        deadline: U256,
+    ) -> Result<SignedPermit> {
+        let owner = signer.address();
+        let token = IERC20Permit::new(self.get_address(), self.provider().clone());
+
+        let nonce = token.nonces(owner).call().await?;
+        let domain = self.permit_domain().await?;
+
+        let permit = Permit { owner, spender, value, nonce, deadline };
+        let signature = message::sign_typed(signer, &permit, &domain).await?;
+
+        Ok(SignedPermit { owner, spender, value, nonce, deadline, signature })
+    }
+
+    /// Sign a one-off Permit2 transfer of up to `amount` for `spender`.
+    /// The owner must have approved `PERMIT2_ADDRESS` on this token once.
+    pub async fn sign_permit2_transfer<S: Signer + Sync>(
+        &self,
+        signer: &S,
+        spender: Address,
+        amount: U256,
+        nonce: U256,
+        deadline: U256,
+    ) -> Result<SignedPermit2Transfer> {
+        let owner = signer.address();
+        let token = self.get_address();
+
+        let permit = PermitTransferFrom {
+            permitted: TokenPermissions { token, amount },
+            spender,
+            nonce,
+            deadline,
+        };
+
+        let domain = permit2_domain(self.provider().get_chain_id().await?);
+        let signature = message::sign_typed(signer, &permit, &domain).await?;
+
+        Ok(SignedPermit2Transfer { owner, token, spender, amount, nonce, deadline, signature })
+    }
+
+    /// True when `owner` has approved Permit2 for at least `amount`
+    pub async fn permit2_ready(&self, owner: Address, amount: U256) -> Result<bool> {
+        Ok(self.get_allowance(owner, PERMIT2_ADDRESS).await? >= amount)
+    }
+}
+
+// Signing methods: only available with a wallet-backed provider
+impl TokenManager<AppProvider> {
+    /// Send `permit` to the token; the wallet pays the gas
+    pub async fn submit_permit(&self, permit: &SignedPermit) -> Result<BroadcastedTransaction> {
+        let token = IERC20Permit::new(self.get_address(), self.provider().clone());
+
+        let request = token
+            .permit(
+                permit.owner,
+                permit.spender,
+                permit.value,
+                permit.deadline,
+                27 + permit.signature.v() as u8,
+                B256::from(permit.signature.r()),
+                B256::from(permit.signature.s()),
+            )
+            .into_transaction_request();
+
+        self.send_prepared(request).await
+    }
+
+    /// Redeem a Permit2 transfer signed for this wallet, sending
+    /// `requested_amount` (at most the signed amount) to `to`
+    pub async fn permit2_transfer(
+        &self,
+        permit: &SignedPermit2Transfer,
+        to: Address,
+        requested_amount: U256,
+    ) -> Result<BroadcastedTransaction> {
+        if permit.spender != self.provider().default_signer_address() {
+            anyhow::bail!("Permit2 transfer is for spender {}, not this wallet", permit.spender);
+        }
+        if permit.token != self.get_address() {
+            anyhow::bail!("Permit2 transfer is for token {}", permit.token);
+        }
+
+        let permit2 = IPermit2::new(PERMIT2_ADDRESS, self.provider().clone());
+
+        let request = permit2
+            .permitTransferFrom(
+                PermitTransferFrom {
+                    permitted: TokenPermissions { token: permit.token, amount: permit.amount },
+                    spender: permit.spender,
+                    nonce: permit.nonce,
+                    deadline: permit.deadline,
+                },
+                SignatureTransferDetails { to, requestedAmount: requested_amount },
+                permit.owner,
+                Bytes::from(permit.signature.as_bytes()),
+            )
+            .into_transaction_request();
+
+        self.send_prepared(request).await
+    }
+}
+
+/// Permit2 signs without a version field
+pub fn permit2_domain(chain_id: u64) -> Eip712Domain {
+    Eip712Domain::new(
+        Some(Cow::Borrowed("Permit2")),
+        None,
+        Some(U256::from(chain_id)),
+        Some(PERMIT2_ADDRESS),
+        None,
+    )
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use alloy::primitives::{Address, address};
+use alloy::providers::Provider;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::token::TokenManager;
+
+/// Known token on one chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenInfo {
+    #[serde(alias = "chainId")]
+    pub chain_id: u64,
+    /// Display symbol; may differ from the contract's (e.g. BSC-USD)
+    pub symbol: String,
+    pub address: Address,
+    pub decimals: u8,
+    pub name: String,
+    #[serde(default, alias = "logoURI", skip_serializing_if = "Option::is_none")]
+    pub logo_uri: Option<String>,
+}
+
+// Registry files: a plain array, or a token list (`{"tokens": [...]}`)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RegistryFile {
+    Tokens(Vec<TokenInfo>),
+    TokenList { tokens: Vec<TokenInfo> },
+}
+
+/// Token metadata keyed by chain id and symbol (case-insensitive)
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    tokens: HashMap<(u64, String), TokenInfo>,
+}
+
+impl TokenRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry of the stablecoins this crate is used with
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+
+        for (chain_id, symbol, address, decimals, name) in DEFAULT_TOKENS {
+            registry.insert(TokenInfo {
+                chain_id,
+                symbol: symbol.to_string(),
+                address,
+                decimals,
+                name: name.to_string(),
+                logo_uri: None,
+            });
+        }
+
+        registry
+    }
+
+    /// Defaults plus the file named by `Config::token_registry`, if any
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut registry = Self::with_defaults();
+
+        if let Some(path) = &config.token_registry {
+            registry.load_file(path)?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Merge entries from a JSON file (see `artifacts/tokens.json`),
+    /// replacing entries with the same chain and symbol. Returns how many
+    /// were read.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+
+        let bytes = std::fs::read(path).with_context(|| format!("Cannot read token registry {path:?}"))?;
+        let tokens = match serde_json::from_slice(&bytes)
+            .with_context(|| format!("Invalid token registry {path:?}"))?
+        {
+            RegistryFile::Tokens(tokens) | RegistryFile::TokenList { tokens } => tokens,
+        };
+
+        let count = tokens.len();
+        for token in tokens {
+            self.insert(token);
+        }
+
+        log::debug!("{count} tokens loaded from {path:?}");
+
+        Ok(count)
+    }
+
+    pub fn insert(&mut self, token: TokenInfo) {
+        self.tokens.insert((token.chain_id, token.symbol.to_uppercase()), token);
+    }
+
+    pub fn get(&self, chain_id: u64, symbol: &str) -> Option<&TokenInfo> {
+        self.tokens.get(&(chain_id, symbol.to_uppercase()))
+    }
+
+    pub fn find_by_address(&self, chain_id: u64, address: Address) -> Option<&TokenInfo> {
+        self.tokens
+            .values()
+            .find(|token| token.chain_id == chain_id && token.address == address)
+    }
+
+    pub fn tokens_for_chain(&self, chain_id: u64) -> Vec<&TokenInfo> {
+        let mut tokens: Vec<&TokenInfo> = self.tokens.values().filter(|t| t.chain_id == chain_id).collect();
+        tokens.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        tokens
+    }
+
+    /// Check that `address` is the registered `symbol` contract on `chain_id`
+    pub fn validate(&self, chain_id: u64, symbol: &str, address: Address) -> Result<&TokenInfo> {
+        let token = self
+            .get(chain_id, symbol)
+            .ok_or_else(|| anyhow::anyhow!("No {symbol} registered for chain {chain_id}"))?;
+
+        if token.address != address {
+            anyhow::bail!(
+                "{symbol} on chain {chain_id} is {}, but {address} is configured",
+                token.address
+            );
+        }
+
+        Ok(token)
+    }
+
+    /// Check `Config::usdt_contract` against the chain the provider is on
+    pub async fn validate_config<P: Provider>(&self, config: &Config, provider: &P) -> Result<&TokenInfo> {
+        let chain_id = provider.get_chain_id().await?;
+
+        self.validate(chain_id, "USDT", config.usdt_contract)
+    }
+}
+
+impl<P: Provider> TokenManager<P> {
+    /// Token manager for `symbol` on the provider's chain, as registered.
+    ///
+    /// Fails when the symbol is unknown for the chain or the contract's
+    /// decimals disagree with the registry.
+    pub async fn from_registry(provider: std::sync::Arc<P>, registry: &TokenRegistry, symbol: &str) -> Result<Self> {
+        let chain_id = provider.get_chain_id().await?;
+
+        let token = registry
+            .get(chain_id, symbol)
+            .ok_or_else(|| anyhow::anyhow!("No {symbol} registered for chain {chain_id}"))?;
+
+        let mut manager = Self::new(provider, token.address).await?;
+
+        if manager.get_decimals() != token.decimals {
+            anyhow::bail!(
+                "{symbol} at {} has {} decimals on-chain, registry says {}",
+                token.address,
+                manager.get_decimals(),
+                token.decimals
+            );
+        }
+
+        manager.set_symbol(&token.symbol);
+
+        Ok(manager)
+    }
+}
+
+// (chain id, symbol, contract, decimals, name)
+const DEFAULT_TOKENS: [(u64, &str, Address, u8, &str); 8] = [
+    (1, "USDT", address!("0xdAC17F958D2ee523a2206206994597C13D831ec7"), 6, "Tether USD"),
+    (1, "USDC", address!("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"), 6, "USD Coin"),
+    (56, "USDT", address!("0x55d398326f99059fF775485246999027B3197955"), 18, "Tether USD"),
+    (56, "USDC", address!("0x8AC76a51cc950d9822D68b83fE1Ad97B32Cd580d"), 18, "USD Coin"),
+    (137, "USDT", address!("0xc2132D05D31c914a87C6611C10748AEb04B58e8F"), 6, "Tether USD"),
+    (137, "USDC", address!("0x3c499c542cEF5E3811e1192ce70d8cC03d5c3359"), 6, "USD Coin"),
+    (42161, "USDT", address!("0xFd086bC7CD5C481DCC9C85ebE478A1C0b69FCbb9"), 6, "Tether USD"),
+    (42161, "USDC", address!("0xaf88d065e77c8cC2239327C5EDb3A432268e5831"), 6, "USD Coin"),
+];
@@ -0,0 +1,541 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::consensus::SignableTransaction;
+use alloy::network::TxSigner;
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{Address, B256, Bytes, ChainId, Signature, eip191_hash_message, keccak256};
+use alloy::signers::Signer;
+use alloy::transports::http::reqwest;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::oneshot;
+use url::Url;
+
+/// Longest request line `serve_unix` accepts
+pub const MAX_SIGN_REQUEST_BYTES: u64 = 1 << 20;
+/// Time a client gets to send its request to `serve_unix`
+pub const SIGN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Request sent to an external signer.
+///
+/// `payload` is what is being signed, so the signing side can apply its own
+/// policy; the signer recomputes the hash from it and refuses a `hash` that
+/// does not match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub address: Address,
+    pub chain_id: Option<ChainId>,
+    pub hash: B256,
+    pub payload: SignPayload,
+}
+
+/// What a `SignRequest` asks to sign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum SignPayload {
+    /// Unsigned transaction as encoded for signing
+    Transaction(Bytes),
+    /// EIP-191 `personal_sign` message
+    Eip191(Bytes),
+    /// EIP-712 typed data (`eth_signTypedData_v4`)
+    Eip712(Box<TypedData>),
+    /// A bare hash with nothing to apply policy to; refused unless the
+    /// signer sets `SignPolicy::allow_raw_hash`
+    Hash,
+}
+
+impl SignPayload {
+    /// Hash a signature over this payload commits to; None for `Hash`
+    pub fn signing_hash(&self) -> Result<Option<B256>> {
+        match self {
+            SignPayload::Transaction(tx) if tx.is_empty() => anyhow::bail!("Empty transaction payload"),
+            SignPayload::Transaction(tx) => Ok(Some(keccak256(tx))),
+            SignPayload::Eip191(message) => Ok(Some(eip191_hash_message(message))),
+            SignPayload::Eip712(typed) => Ok(Some(
+                typed.eip712_signing_hash().map_err(|e| anyhow::anyhow!("Invalid typed data: {e}"))?,
+            )),
+            SignPayload::Hash => Ok(None),
+        }
+    }
+}
+
+/// What the signing side agrees to sign
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignPolicy {
+    /// Sign `SignPayload::Hash` requests, whose content cannot be checked
+    pub allow_raw_hash: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignResponse {
+    /// 65-byte r || s || v signature
+    pub signature: Option<Bytes>,
+    pub error: Option<String>,
+}
+
+/// Transport to a process that holds the key.
+pub trait SigningBackend: Send + Sync {
+    fn sign(&self, request: &SignRequest) -> impl Future<Output = Result<Signature>> + Send;
+}
+
+/// `TxSigner` whose key lives outside this process.
+///
+/// Wrap in `EthereumWallet` (or pass to `EvmClient::with_signer`) to use it
+/// anywhere a `PrivateKeySigner` is used today.
+pub struct ExternalSigner<B> {
+    address: Address,
+    chain_id: Option<ChainId>,
+    backend: B,
+}
+
+impl<B: SigningBackend> ExternalSigner<B> {
+    pub fn new(address: Address, backend: B) -> Self {
+        Self { address, chain_id: None, backend }
+    }
+
+    // Sign and make sure the backend answered for the right key
+    async fn sign_checked(&self, hash: B256, payload: SignPayload) -> Result<Signature> {
+        let request = SignRequest { address: self.address, chain_id: self.chain_id, hash, payload };
+
+        let signature = self.backend.sign(&request).await?;
+
+        let recovered = signature.recover_address_from_prehash(&hash)?;
+        if recovered != self.address {
+            anyhow::bail!("External signer returned a signature for {recovered}, expected {}", self.address);
+        }
+
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl<B: SigningBackend> TxSigner<Signature> for ExternalSigner<B> {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        if let Some(chain_id) = self.chain_id
+            && !tx.set_chain_id_checked(chain_id)
+        {
+            return Err(alloy::signers::Error::TransactionChainIdMismatch {
+                signer: chain_id,
+                tx: tx.chain_id().unwrap_or_default(),
+            });
+        }
+
+        let payload = SignPayload::Transaction(tx.encoded_for_signing().into());
+
+        self.sign_checked(tx.signature_hash(), payload)
+            .await
+            .map_err(|e| alloy::signers::Error::other(e.to_string()))
+    }
+}
+
+// Messages and typed data go out with their content; only `sign_hash`
+// (and `sign_typed_data`, which alloy routes through it) sends a bare hash
+#[async_trait]
+impl<B: SigningBackend> Signer for ExternalSigner<B> {
+    async fn sign_hash(&self, hash: &B256) -> alloy::signers::Result<Signature> {
+        self.sign_checked(*hash, SignPayload::Hash)
+            .await
+            .map_err(|e| alloy::signers::Error::other(e.to_string()))
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> alloy::signers::Result<Signature> {
+        let payload = SignPayload::Eip191(Bytes::copy_from_slice(message));
+
+        self.sign_checked(eip191_hash_message(message), payload)
+            .await
+            .map_err(|e| alloy::signers::Error::other(e.to_string()))
+    }
+
+    async fn sign_dynamic_typed_data(&self, typed: &TypedData) -> alloy::signers::Result<Signature> {
+        let hash = typed.eip712_signing_hash()?;
+
+        self.sign_checked(hash, SignPayload::Eip712(Box::new(typed.clone())))
+            .await
+            .map_err(|e| alloy::signers::Error::other(e.to_string()))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
+    }
+}
+
+/// Remote signing service: `POST <url>` with a JSON `SignRequest`,
+/// answered by a JSON `SignResponse`.
+pub struct HttpSigningBackend {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl HttpSigningBackend {
+    pub fn new(url: Url) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    /// Use a preconfigured client (auth headers, TLS, timeouts)
+    pub fn with_client(client: reqwest::Client, url: Url) -> Self {
+        Self { client, url }
+    }
+}
+
+impl SigningBackend for HttpSigningBackend {
+    async fn sign(&self, request: &SignRequest) -> Result<Signature> {
+        let body = serde_json::to_vec(request)?;
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let bytes = response.bytes().await?;
+
+        parse_response(&bytes)
+    }
+}
+
+/// Signer process listening on a Unix socket; one JSON line per request and response.
+pub struct UnixSocketSigningBackend {
+    path: PathBuf,
+}
+
+impl UnixSocketSigningBackend {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+impl SigningBackend for UnixSocketSigningBackend {
+    async fn sign(&self, request: &SignRequest) -> Result<Signature> {
+        let stream = UnixStream::connect(&self.path)
+            .await
+            .with_context(|| format!("Could not connect to signer socket {:?}", self.path))?;
+        let (reader, mut writer) = stream.into_split();
+
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+
+        let mut response = String::new();
+        BufReader::new(reader).read_line(&mut response).await?;
+
+        parse_response(response.as_bytes())
+    }
+}
+
+fn parse_response(bytes: &[u8]) -> Result<Signature> {
+    let response: SignResponse = serde_json::from_slice(bytes)?;
+
+    if let Some(error) = response.error {
+        anyhow::bail!("External signer refused: {error}");
+    }
+
+    let raw = response.signature.ok_or_else(|| anyhow::anyhow!("External signer returned no signature"))?;
+
+    Ok(Signature::from_raw(&raw)?)
+}
+
+/// Answer one request with a local signer.
+///
+/// Building block for the signer process and for stand-in servers in tests.
+/// Refuses requests for an address other than the signer's, payloads that
+/// do not hash to `hash`, and bare hashes unless `policy` allows them.
+pub async fn handle_sign_request<S: Signer + Sync>(
+    signer: &S,
+    request: &SignRequest,
+    policy: SignPolicy,
+) -> SignResponse {
+    let refuse = |error: String| SignResponse { signature: None, error: Some(error) };
+
+    if request.address != signer.address() {
+        return refuse(format!("Unknown address {}", request.address));
+    }
+
+    // Policy runs on the payload, so the hash must be the payload's
+    let hash = match request.payload.signing_hash() {
+        Ok(Some(hash)) => hash,
+        Ok(None) if policy.allow_raw_hash => request.hash,
+        Ok(None) => return refuse("Raw hash signing is disabled".to_string()),
+        Err(e) => return refuse(e.to_string()),
+    };
+    if hash != request.hash {
+        return refuse("Hash does not match payload".to_string());
+    }
+
+    match signer.sign_hash(&hash).await {
+        Ok(signature) => SignResponse { signature: Some(Bytes::from(signature.as_bytes())), error: None },
+        Err(e) => SignResponse { signature: None, error: Some(e.to_string()) },
+    }
+}
+
+/// Run the signer side on a Unix socket until `shutdown` fires.
+///
+/// Each connection is served in its own task and must send its request
+/// within `SIGN_REQUEST_TIMEOUT`; failed accepts are logged and retried.
+pub async fn serve_unix<S: Signer + Send + Sync + 'static>(
+    path: impl AsRef<Path>,
+    signer: Arc<S>,
+    policy: SignPolicy,
+    mut shutdown: oneshot::Receiver<()>,
+) -> Result<()> {
+    let listener = UnixListener::bind(path.as_ref())?;
+    log::debug!("Signer listening on {:?}", path.as_ref());
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => {
+                log::info!("Signer shutting down...");
+                break;
+            }
+
+            accepted = listener.accept() => {
+                let stream = match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        log::error!("Signer accept failed: {e}");
+                        // e.g. out of file descriptors; do not spin
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+
+                let signer = Arc::clone(&signer);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, signer.as_ref(), policy).await {
+                        log::warn!("Sign request failed: {e}");
+                    }
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn serve_connection<S: Signer + Sync>(stream: UnixStream, signer: &S, policy: SignPolicy) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+
+    let mut line = String::new();
+    let mut reader = BufReader::new(reader.take(MAX_SIGN_REQUEST_BYTES));
+    tokio::time::timeout(SIGN_REQUEST_TIMEOUT, reader.read_line(&mut line))
+        .await
+        .context("Timed out reading sign request")??;
+
+    let response = if !line.ends_with('\n') {
+        SignResponse { signature: None, error: Some("Request too large or incomplete".to_string()) }
+    } else {
+        match serde_json::from_str::<SignRequest>(&line) {
+            Ok(request) => handle_sign_request(signer, &request, policy).await,
+            Err(e) => SignResponse { signature: None, error: Some(format!("Bad request: {e}")) },
+        }
+    };
+
+    let mut out = serde_json::to_vec(&response)?;
+    out.push(b'\n');
+    writer.write_all(&out).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::consensus::TxEip1559;
+    use alloy::primitives::{TxKind, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::sol_types::eip712_domain;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::permit::Permit;
+    use crate::wallet::message;
+
+    fn transaction() -> TxEip1559 {
+        TxEip1559 {
+            chain_id: 56,
+            nonce: 7,
+            gas_limit: 21_000,
+            max_fee_per_gas: 3_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::from(1_000u64),
+            ..Default::default()
+        }
+    }
+
+    async fn assert_round_trip<B: SigningBackend>(external: ExternalSigner<B>, address: Address) {
+        let mut tx = transaction();
+        let signature = external.sign_transaction(&mut tx).await.unwrap();
+        assert_eq!(signature.recover_address_from_prehash(&tx.signature_hash()).unwrap(), address);
+
+        let signature = external.sign_message(b"withdrawal 42").await.unwrap();
+        assert_eq!(signature.recover_address_from_msg(b"withdrawal 42").unwrap(), address);
+
+        let domain = eip712_domain! {
+            name: "Tether USD",
+            version: "1",
+            chain_id: 56,
+            verifying_contract: Address::repeat_byte(0x55),
+        };
+        let permit = Permit {
+            owner: address,
+            spender: Address::repeat_byte(0x22),
+            value: U256::from(5u64),
+            nonce: U256::ZERO,
+            deadline: U256::from(u64::MAX),
+        };
+        let signature = message::sign_typed(&external, &permit, &domain).await.unwrap();
+        assert!(message::verify_typed(&permit, &domain, &signature, address).unwrap());
+
+        // Bare hashes are refused by default
+        let refused = external.sign_hash(&tx.signature_hash()).await.unwrap_err();
+        assert!(refused.to_string().contains("Raw hash signing is disabled"), "{refused}");
+    }
+
+    // Stand-in HTTP signing service: one request per connection
+    async fn serve_http(signer: Arc<PrivateKeySigner>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/sign", listener.local_addr().unwrap())).unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (reader, mut writer) = stream.into_split();
+                let mut reader = BufReader::new(reader);
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).await.unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).await.unwrap();
+
+                let request: SignRequest = serde_json::from_slice(&body).unwrap();
+                let response = serde_json::to_vec(&handle_sign_request(signer.as_ref(), &request, SignPolicy::default()).await).unwrap();
+
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.len()
+                );
+                writer.write_all(head.as_bytes()).await.unwrap();
+                writer.write_all(&response).await.unwrap();
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn http_backend_round_trip() {
+        let signer = Arc::new(PrivateKeySigner::random());
+        let address = signer.address();
+        let url = serve_http(signer).await;
+
+        assert_round_trip(ExternalSigner::new(address, HttpSigningBackend::new(url.clone())), address).await;
+
+        // The service refuses keys it does not hold
+        let other = Address::repeat_byte(0x22);
+        let refused = ExternalSigner::new(other, HttpSigningBackend::new(url)).sign_hash(&B256::ZERO).await;
+        assert!(refused.unwrap_err().to_string().contains("Unknown address"));
+    }
+
+    #[tokio::test]
+    async fn unix_backend_round_trip_with_idle_client() {
+        let path = std::env::temp_dir().join(format!("ether-signer-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let signer = Arc::new(PrivateKeySigner::random());
+        let address = signer.address();
+        let (stop, shutdown) = oneshot::channel();
+        let server = tokio::spawn(serve_unix(path.clone(), signer, SignPolicy::default(), shutdown));
+        tokio::task::yield_now().await;
+
+        // A client that never sends must not hold up others
+        let _idle = UnixStream::connect(&path).await.unwrap();
+
+        assert_round_trip(ExternalSigner::new(address, UnixSocketSigningBackend::new(&path)), address).await;
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_hash_not_matching_payload() {
+        let signer = PrivateKeySigner::random();
+        let mut tx = transaction();
+        let policy = SignPolicy::default();
+
+        let mut request = SignRequest {
+            address: signer.address(),
+            chain_id: Some(56),
+            hash: tx.signature_hash(),
+            payload: SignPayload::Transaction(tx.encoded_for_signing().into()),
+        };
+        assert!(handle_sign_request(&signer, &request, policy).await.signature.is_some());
+
+        // Policy approved this payload, but a different transaction is signed
+        tx.value = U256::from(1_000_000u64);
+        request.hash = tx.signature_hash();
+
+        let response = handle_sign_request(&signer, &request, policy).await;
+        assert!(response.signature.is_none());
+        assert_eq!(response.error.as_deref(), Some("Hash does not match payload"));
+    }
+
+    #[tokio::test]
+    async fn refuses_transaction_hash_without_payload() {
+        let signer = PrivateKeySigner::random();
+        let hash = transaction().signature_hash();
+
+        let empty = SignRequest {
+            address: signer.address(),
+            chain_id: Some(56),
+            hash,
+            payload: SignPayload::Transaction(Bytes::new()),
+        };
+        let response = handle_sign_request(&signer, &empty, SignPolicy::default()).await;
+        assert!(response.signature.is_none());
+        assert_eq!(response.error.as_deref(), Some("Empty transaction payload"));
+
+        let raw = SignRequest { payload: SignPayload::Hash, ..empty };
+        let response = handle_sign_request(&signer, &raw, SignPolicy::default()).await;
+        assert!(response.signature.is_none());
+        assert_eq!(response.error.as_deref(), Some("Raw hash signing is disabled"));
+
+        // Only an explicit opt-in signs bare hashes
+        let response = handle_sign_request(&signer, &raw, SignPolicy { allow_raw_hash: true }).await;
+        assert!(response.signature.is_some());
+    }
+}
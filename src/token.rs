@@ -1,24 +1,56 @@
 use std::convert::TryInto;
 use std::sync::Arc;
 use alloy::eips::BlockId;
-use alloy::network::ReceiptResponse;
-use alloy::rpc::types::TransactionReceipt;
-use alloy::primitives::{Address, TxHash, U256};
-use alloy::providers::Provider;
+use alloy::eips::eip2718::Encodable2718;
+use alloy::consensus::Transaction as ConsensusTransaction;
+use alloy::network::{TransactionBuilder, TransactionResponse as _};
+use alloy::rpc::types::state::{StateOverride, StateOverridesBuilder};
+use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use alloy::sol_types::SolValue;
+use alloy::transports::{RpcError, TransportError};
+use alloy::primitives::{Address, B256, Bytes, I256, TxHash, U256, keccak256};
+use alloy::providers::{Provider, WalletProvider};
 use anyhow::Result;
+use futures::StreamExt;
+use futures::stream::BoxStream;
 
 use crate::client::AppProvider;
-use crate::components::{BroadcastedTransaction, IERC20, PreparedTransfer};
+use crate::components::{BroadcastedTransaction, Erc20ReturnError, FeeDetails, FeeMode, IERC20, IERC20Bytes32, InsufficientFunds, NonceConsumed, PreparedTransfer, ReceiptOutcome, ReplacementGroup, RevertReason, SignedTransfer, TransactionFailure, TransferAmounts};
+use crate::fees::{FeeHistoryStrategy, FeeStrategy};
+use crate::nonce::{self, NonceManager};
+use crate::offline::{self, UnsignedTransfer};
 use crate::utils;
 
-pub struct TokenManager {
-    contract: IERC20::IERC20Instance<Arc<AppProvider>>,
+/// Gas estimates `prepare_native_sweep` makes before giving up
+const SWEEP_ESTIMATE_ROUNDS: usize = 4;
+
+/// Smallest fee increase (percent) nodes accept for a same-nonce replacement
+pub const MIN_REPLACEMENT_BUMP_PERCENT: u64 = 10;
+
+/// Blocks a pending transaction may be unknown to the node before it counts
+/// as dropped; tolerates load-balanced RPCs with differing mempools
+pub const DROPPED_AFTER_BLOCKS: u64 = 3;
+
+/// ERC-20 helper over any provider.
+///
+/// Read methods work with every provider (see `ReadOnlyClient`); signing
+/// methods are only implemented for the wallet-backed `AppProvider`.
+pub struct TokenManager<P = AppProvider> {
+    contract: IERC20::IERC20Instance<Arc<P>>,
     decimals: u8,
+    name: String,
     symbol: String,
+    // None = detect from the latest block on every estimate
+    fee_mode: Option<FeeMode>,
+    fee_strategy: Arc<dyn FeeStrategy<P>>,
+    // None = the provider's NonceFiller asks the node for every transaction
+    nonce_manager: Option<Arc<NonceManager>>,
 }
 
-impl TokenManager {
-    pub async fn new(provider: Arc<AppProvider>, contract_address: Address, symbol: &str) -> Result<Self> {
+impl<P: Provider> TokenManager<P> {
+    /// Connect to the token at `contract_address`, reading decimals, name
+    /// and symbol from the chain
+    pub async fn new(provider: Arc<P>, contract_address: Address) -> Result<Self> {
         let contract = IERC20::new(contract_address, provider.clone());
 
         // Cache decimals for parsing
@@ -34,13 +66,79 @@ impl TokenManager {
             }
         };
 
-        Ok(Self { contract, decimals, symbol: symbol.to_string() })
+        let legacy = IERC20Bytes32::new(contract_address, provider.clone());
+
+        // name and symbol are optional in the standard
+        let name = match contract.name().call().await {
+            Ok(name) => name,
+            Err(_) => legacy.name().call().await.map(bytes32_to_string).unwrap_or_else(|e| {
+                log::warn!("name_call error: {e}");
+                String::new()
+            }),
+        };
+        let symbol = match contract.symbol().call().await {
+            Ok(symbol) => symbol,
+            Err(_) => legacy.symbol().call().await.map(bytes32_to_string).unwrap_or_else(|e| {
+                log::warn!("symbol_call error: {e}");
+                String::new()
+            }),
+        };
+
+        log::debug!("token {contract_address}: {name} ({symbol}), {decimals} decimals");
+
+        Ok(Self {
+            contract,
+            decimals,
+            name,
+            symbol,
+            fee_mode: None,
+            fee_strategy: Arc::new(FeeHistoryStrategy::default()),
+            nonce_manager: None,
+        })
     }
 
     pub fn get_decimals(&self) -> u8 {
         self.decimals
     }
 
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Override the on-chain symbol, e.g. for tokens that do not expose one
+    pub fn set_symbol(&mut self, symbol: &str) {
+        self.symbol = symbol.to_string();
+    }
+
+    pub fn get_address(&self) -> Address {
+        *self.contract.address()
+    }
+
+    pub(crate) fn provider(&self) -> &Arc<P> {
+        self.contract.provider()
+    }
+
+    /// Force a fee mode, e.g. `Legacy` for RPCs that reject type-2
+    /// transactions; `None` restores auto-detection
+    pub fn set_fee_mode(&mut self, fee_mode: Option<FeeMode>) {
+        self.fee_mode = fee_mode;
+    }
+
+    /// Replace the default `FeeHistoryStrategy` (normal speed, no caps)
+    pub fn set_fee_strategy(&mut self, strategy: Arc<dyn FeeStrategy<P>>) {
+        self.fee_strategy = strategy;
+    }
+
+    /// Allocate nonces locally for concurrent broadcasts; share one manager
+    /// between all token managers that send from the same wallet
+    pub fn set_nonce_manager(&mut self, nonce_manager: Option<Arc<NonceManager>>) {
+        self.nonce_manager = nonce_manager;
+    }
+
     pub async fn get_balance_raw(&self, address: Address) -> Result<U256> {
         let bal = self.contract.balanceOf(address).call().await?;
 
@@ -53,31 +151,171 @@ impl TokenManager {
         Ok(bal)
     }
 
+    /// `get_balance_raw` at a past block, e.g. `BlockId::finalized()`.
+    /// Older blocks need an archive node.
+    pub async fn get_balance_raw_at(&self, address: Address, block: BlockId) -> Result<U256> {
+        let bal = self.contract.balanceOf(address).block(block).call().await?;
+
+        Ok(bal)
+    }
+
+    pub async fn get_chain_balance_raw_at(&self, address: Address, block: BlockId) -> Result<U256> {
+        let bal = self.contract.provider().get_balance(address).block_id(block).await?;
+
+        Ok(bal)
+    }
+
+    /// Token balance as of `timestamp` (UNIX seconds), i.e. at the last
+    /// block mined at or before it
+    pub async fn get_balance_raw_at_timestamp(&self, address: Address, timestamp: u64) -> Result<U256> {
+        let block = self.block_at_timestamp(timestamp).await?;
+
+        self.get_balance_raw_at(address, BlockId::number(block)).await
+    }
+
+    pub async fn get_chain_balance_raw_at_timestamp(&self, address: Address, timestamp: u64) -> Result<U256> {
+        let block = self.block_at_timestamp(timestamp).await?;
+
+        self.get_chain_balance_raw_at(address, BlockId::number(block)).await
+    }
+
+    /// Number of the last block with a timestamp at or before `timestamp`,
+    /// found by binary search over block headers
+    pub async fn block_at_timestamp(&self, timestamp: u64) -> Result<u64> {
+        let mut hi = self.contract.provider().get_block_number().await?;
+        if self.block_timestamp(hi).await? <= timestamp {
+            return Ok(hi);
+        }
+
+        let mut lo = 0;
+        if self.block_timestamp(lo).await? > timestamp {
+            anyhow::bail!("Timestamp {timestamp} is before the genesis block");
+        }
+
+        // Invariant: block `lo` is at or before `timestamp`, block `hi` after it
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+
+            if self.block_timestamp(mid).await? <= timestamp {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        log::debug!("block at timestamp {timestamp}: {lo}");
+
+        Ok(lo)
+    }
+
+    async fn block_timestamp(&self, number: u64) -> Result<u64> {
+        let block = self
+            .contract
+            .provider()
+            .get_block(BlockId::number(number))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Block {number} not found"))?;
+
+        Ok(block.header.timestamp)
+    }
+
+    pub async fn get_total_supply(&self) -> Result<U256> {
+        let supply = self.contract.totalSupply().call().await?;
+
+        Ok(supply)
+    }
+
+    pub async fn get_allowance(&self, owner: Address, spender: Address) -> Result<U256> {
+        let allowance = self.contract.allowance(owner, spender).call().await?;
+
+        Ok(allowance)
+    }
+
     pub async fn get_balance_human(&self, address: Address) -> Result<String> {
         let bal = self.contract.balanceOf(address).call().await?;
         Ok(format!("{} {}", utils::to_human(bal, self.decimals)?, self.symbol))
     }
 
-    /// Helper: compute max_fee and priority_fee (EIP-1559)
-    async fn estimate_eip1559_fees(
-        provider: &Arc<AppProvider>,
-    ) -> Result<(U256, U256)> {
+    /// Helper: pick the fee mode and price it with the configured strategy
+    async fn estimate_fees(&self) -> Result<(FeeMode, U256, U256, FeeDetails)> {
+        let provider = self.contract.provider();
+
+        let mode = match self.fee_mode {
+            Some(mode) => mode,
+            None => Self::detect_fee_mode(provider).await?,
+        };
+
+        let estimate = self.fee_strategy.estimate(provider, mode).await?;
+
+        Ok((mode, estimate.max_fee_per_gas, estimate.max_priority_fee_per_gas, estimate.details))
+    }
+
+    /// Chains without `base_fee_per_gas` in their blocks are legacy-priced
+    async fn detect_fee_mode(provider: &Arc<P>) -> Result<FeeMode> {
         let block = provider
             .get_block(BlockId::latest())
             .await?
             .ok_or_else(|| anyhow::anyhow!("No latest block"))?;
 
-        let base_fee = block
-            .header
-            .base_fee_per_gas
-            .ok_or_else(|| anyhow::anyhow!("Chain does not support EIP-1559"))?;
+        let mode = match block.header.base_fee_per_gas {
+            Some(_) => FeeMode::Eip1559,
+            None => FeeMode::Legacy,
+        };
+
+        log::debug!("fee mode: {mode:?}");
+
+        Ok(mode)
+    }
+
+    /// Amount `to` received from this token in `receipt`, per `Transfer` logs
+    pub fn received_in_receipt(&self, receipt: &TransactionReceipt, to: Address) -> U256 {
+        receipt
+            .inner
+            .logs()
+            .iter()
+            .filter(|log| log.address() == *self.contract.address())
+            .filter_map(|log| log.log_decode::<IERC20::Transfer>().ok())
+            .filter(|event| event.inner.to == to)
+            .fold(U256::ZERO, |sum, event| sum.saturating_add(event.inner.value))
+    }
+
+    /// What `to` actually got from a mined transfer of `requested`
+    pub async fn transfer_amounts(
+        &self,
+        receipt: &TransactionReceipt,
+        to: Address,
+        requested: U256,
+    ) -> Result<TransferAmounts> {
+        let received = self.received_in_receipt(receipt, to);
+
+        let balance_delta = match receipt.block_number {
+            Some(block) if block > 0 => self.balance_delta(to, block).await,
+            _ => None,
+        };
 
-        let priority_fee = U256::from(2_000_000_000u64); // 2 gwei
+        if received < requested {
+            log::warn!("transfer to {to}: requested {requested}, received {received}");
+        }
+
+        Ok(TransferAmounts {
+            requested,
+            received,
+            balance_delta,
+        })
+    }
 
-        let base_fee_u256 = U256::from(base_fee);
-        let max_fee_per_gas = base_fee_u256.checked_mul(U256::from(2)).unwrap() + priority_fee;
+    // Change of `balanceOf(account)` across `block`; None without archive state
+    async fn balance_delta(&self, account: Address, block: u64) -> Option<I256> {
+        let before = self.get_balance_raw_at(account, BlockId::number(block - 1));
+        let after = self.get_balance_raw_at(account, BlockId::number(block));
 
-        Ok((max_fee_per_gas, priority_fee))
+        match futures::future::try_join(before, after).await {
+            Ok((before, after)) => Some(I256::from_raw(after).wrapping_sub(I256::from_raw(before))),
+            Err(e) => {
+                log::debug!("historical balanceOf unavailable: {e}");
+                None
+            }
+        }
     }
 
     /// Cost estimates
@@ -86,51 +324,203 @@ impl TokenManager {
         to: Address,
         amount_wei: U256,
     ) -> Result<PreparedTransfer> {
-        let call = self.contract.transfer(to, amount_wei);
+        let request = self.contract.transfer(to, amount_wei).into_transaction_request();
+
+        self.prepare_request(request).await
+    }
+
+    /// `prepare_transfer` as `from`, failing early with `InsufficientFunds`
+    /// when `from` lacks the tokens or the native coin for the maximum fee
+    pub async fn prepare_transfer_checked(
+        &self,
+        from: Address,
+        to: Address,
+        amount_wei: U256,
+    ) -> Result<PreparedTransfer> {
+        // Before estimating: a short balance would only surface as a revert
+        self.check_token_funds(from, amount_wei).await?;
+
+        let request = self.contract.transfer(to, amount_wei).from(from).into_transaction_request();
+        let prepared = self.prepare_request(request).await?;
+
+        self.check_gas_funds(from, &prepared).await?;
+
+        Ok(prepared)
+    }
+
+    /// Check that `from` can send `amount_wei` and pay for `prepared`
+    pub async fn check_funds(&self, from: Address, amount_wei: U256, prepared: &PreparedTransfer) -> Result<()> {
+        self.check_token_funds(from, amount_wei).await?;
+        self.check_gas_funds(from, prepared).await
+    }
+
+    async fn check_token_funds(&self, from: Address, amount_wei: U256) -> Result<()> {
+        let balance = self.get_balance_raw(from).await?;
+
+        if balance < amount_wei {
+            return Err(InsufficientFunds::Token { account: from, balance, required: amount_wei }.into());
+        }
+
+        Ok(())
+    }
+
+    async fn check_gas_funds(&self, from: Address, prepared: &PreparedTransfer) -> Result<()> {
+        let (fee_wei, _) = prepared.calculate_fee(None)?;
+        let balance = self.get_chain_balance_raw(from).await?;
+
+        if balance < fee_wei {
+            return Err(InsufficientFunds::Gas { account: from, balance, required: fee_wei }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Cost estimates for sending `amount_wei` of the native coin from `from`
+    pub async fn prepare_native_transfer(
+        &self,
+        from: Address,
+        to: Address,
+        amount_wei: U256,
+    ) -> Result<PreparedTransfer> {
+        let request = TransactionRequest::default()
+            .with_from(from)
+            .with_to(to)
+            .with_value(amount_wei);
+
+        self.prepare_request(request).await
+    }
 
-        let gas_estimate = call.estimate_gas().await?;
+    /// Run a token call with `eth_call` and check its return value.
+    ///
+    /// Fails with `Erc20ReturnError` (reachable via `downcast_ref`) when the
+    /// token would return `false` or malformed data.
+    pub async fn simulate_token_call(&self, request: TransactionRequest) -> Result<()> {
+        self.simulate(request, None).await
+    }
+
+    /// Dry-run a transfer from `from` before anything is signed.
+    ///
+    /// Reverts (paused or blacklisting tokens, missing balance) fail with a
+    /// decoded `RevertReason`; `overrides` (see `balance_override`) let an
+    /// unfunded account be simulated.
+    pub async fn simulate_transfer(
+        &self,
+        from: Address,
+        to: Address,
+        amount_wei: U256,
+        overrides: Option<StateOverride>,
+    ) -> Result<()> {
+        let request = self.contract.transfer(to, amount_wei).from(from).into_transaction_request();
 
-        let (max_fee_per_gas, max_priority_fee_per_gas) =
-            Self::estimate_eip1559_fees(self.contract.provider()).await?;
+        self.simulate(request, overrides).await
+    }
+
+    /// State override giving `holder` `amount_wei` tokens and
+    /// `native_balance` for gas. `balance_slot` is the storage slot of the
+    /// token's balances mapping (Solidity layout), e.g. 0 for OpenZeppelin 4.
+    pub fn balance_override(
+        &self,
+        holder: Address,
+        balance_slot: u64,
+        amount_wei: U256,
+        native_balance: U256,
+    ) -> StateOverride {
+        let key = keccak256((holder, U256::from(balance_slot)).abi_encode());
+
+        StateOverridesBuilder::default()
+            .with_state_diff(*self.contract.address(), [(key, B256::from(amount_wei))])
+            .with_balance(holder, native_balance)
+            .build()
+    }
+
+    async fn simulate(&self, request: TransactionRequest, overrides: Option<StateOverride>) -> Result<()> {
+        let data = self
+            .contract
+            .provider()
+            .call(request)
+            .overrides_opt(overrides)
+            .await
+            .map_err(call_error)?;
+
+        Erc20ReturnError::check(&data)?;
+
+        Ok(())
+    }
+
+    // Gas estimate for `request` plus fees from the fee strategy
+    pub(crate) async fn prepare_request(&self, request: TransactionRequest) -> Result<PreparedTransfer> {
+        let gas_estimate = self.contract.provider().estimate_gas(request).await.map_err(call_error)?;
+
+        let (fee_mode, max_fee_per_gas, max_priority_fee_per_gas, fee_details) = self.estimate_fees().await?;
 
         Ok(PreparedTransfer {
             gas_estimate,
+            fee_mode,
+            fee_details,
             max_fee_per_gas,
             max_priority_fee_per_gas,
         })
     }
 
-    pub async fn broadcast_transfer(
-        &self,
-        to: Address,
-        amount_wei: U256,
-        prepared: &PreparedTransfer,
-    ) -> Result<BroadcastedTransaction> {
+    /// Cost estimates for sending the whole native balance of `from`.
+    ///
+    /// Returns the amount to send: balance minus `gas_estimate * max_fee_per_gas`.
+    /// Gas is estimated with that amount as the value, since a contract
+    /// recipient may branch on `msg.value`. With EIP-1559 the unused part of
+    /// the max fee is refunded, so a little dust can remain.
+    pub async fn prepare_native_sweep(&self, from: Address, to: Address) -> Result<(U256, PreparedTransfer)> {
         let provider = self.contract.provider();
 
-        let submitted_block = provider.get_block_number().await?;
+        let balance = self.get_chain_balance_raw(from).await?;
+        let (fee_mode, max_fee_per_gas, max_priority_fee_per_gas, fee_details) = self.estimate_fees().await?;
 
-        let max_fee_u128 = prepared.max_fee_per_gas.try_into()
-            .map_err(|_| anyhow::anyhow!("max_fee_per_gas overflowed u128"))?;
-        let max_priority_u128 = prepared.max_priority_fee_per_gas.try_into()
-            .map_err(|_| anyhow::anyhow!("max_priority_fee_per_gas overflowed u128"))?;
+        // Extra funds for the estimate only, so the node does not reject
+        // sending the whole balance for missing fee funds
+        let overrides = StateOverridesBuilder::default()
+            .with_balance(from, balance.saturating_mul(U256::from(2)))
+            .build();
 
-        let tx = self
-            .contract
-            .transfer(to, amount_wei)
-            // .max_fee_per_gas(max_fee.to::<u128>())
-            // .max_priority_fee_per_gas(max_priority.to::<u128>())
-            .max_fee_per_gas(max_fee_u128)
-            .max_priority_fee_per_gas(max_priority_u128)
-            .send()
-            .await?;
+        let mut amount = balance;
+        let mut gas_estimate = 0;
+        for _ in 0..SWEEP_ESTIMATE_ROUNDS {
+            let request = TransactionRequest::default().with_from(from).with_to(to).with_value(amount);
+            let estimate = provider
+                .estimate_gas(request)
+                .overrides(overrides.clone())
+                .await
+                .map_err(call_error)?;
+            gas_estimate = gas_estimate.max(estimate);
 
-        Ok(BroadcastedTransaction {
-            hash: *tx.tx_hash(),
-            submitted_block,
-        })
+            let fee = max_fee_per_gas
+                .checked_mul(U256::from(gas_estimate))
+                .ok_or_else(|| anyhow::anyhow!("Fee overflowed"))?;
+
+            let swept = balance
+                .checked_sub(fee)
+                .filter(|swept| !swept.is_zero())
+                .ok_or_else(|| anyhow::anyhow!("Balance {balance} of {from} does not cover the fee {fee}"))?;
+
+            // Done once the gas limit covers the amount actually sent
+            if swept == amount {
+                let prepared = PreparedTransfer {
+                    gas_estimate,
+                    fee_mode,
+                    fee_details,
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                };
+                return Ok((amount, prepared));
+            }
+
+            amount = swept;
+        }
+
+        anyhow::bail!("Gas for sweeping {from} to {to} depends on the amount and did not settle")
     }
 
+    /// Wait for inclusion; None when the transaction was not mined within
+    /// `max_blocks_wait` blocks, or was dropped or replaced.
+    /// See `wait_for_confirmations` for details.
     pub async fn wait_for_receipt(
         &self,
         hash: TxHash,
@@ -138,29 +528,670 @@ impl TokenManager {
         max_blocks_wait: u64,
         wait_time: u64,
     ) -> Result<Option<TransactionReceipt>> {
+        match self.wait_for_confirmations(hash, submitted_block, 1, max_blocks_wait, wait_time).await? {
+            ReceiptOutcome::Confirmed(receipt) => Ok(Some(*receipt)),
+            outcome => {
+                // tx is very likely stuck / underpriced
+                log::warn!("transaction {hash} not mined: {outcome:?}");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Wait until `hash` is `confirmations` blocks deep (1 = included).
+    ///
+    /// Wakes on `newHeads` when the provider is WebSocket-connected and
+    /// otherwise polls for new blocks every `poll_interval` seconds. Reports
+    /// a replacement once the sender's nonce is used by another
+    /// transaction, and a drop once the node no longer knows the transaction.
+    /// A reverted receipt fails with `TransactionFailure`.
+    pub async fn wait_for_confirmations(
+        &self,
+        hash: TxHash,
+        submitted_block: u64,
+        confirmations: u64,
+        max_blocks_wait: u64,
+        poll_interval: u64,
+    ) -> Result<ReceiptOutcome> {
         let provider = self.contract.provider();
 
+        let mut heads = self.new_heads(poll_interval).await?;
+        let mut head = provider.get_block_number().await?;
+
+        // Learned from the pending transaction, to tell replaced from dropped
+        let mut sender_nonce = None;
+        // Blocks, not polls: the timer can fire several times per block
+        let mut missing_blocks = 0;
+        let mut missing_at = None;
+
         loop {
             if let Some(receipt) = provider.get_transaction_receipt(hash).await? {
-                receipt.ensure_success()?;
-                return Ok(Some(receipt));
+                if !receipt.status() {
+                    return Err(self.transaction_failure(&receipt).await?.into());
+                }
+
+                let included = receipt.block_number.unwrap_or(head);
+                if head + 1 >= included + confirmations.max(1) {
+                    return Ok(ReceiptOutcome::Confirmed(Box::new(receipt)));
+                }
+            } else {
+                match provider.get_transaction_by_hash(hash).await? {
+                    Some(tx) => {
+                        sender_nonce = Some((tx.from(), ConsensusTransaction::nonce(&tx)));
+                        missing_blocks = 0;
+                        missing_at = None;
+                    }
+                    None if missing_at != Some(head) => {
+                        missing_blocks += 1;
+                        missing_at = Some(head);
+                    }
+                    None => {}
+                }
+
+                if let Some((sender, nonce)) = sender_nonce
+                    && provider.get_transaction_count(sender).latest().await? > nonce
+                    // It may have been mined since the receipt lookup
+                    && provider.get_transaction_receipt(hash).await?.is_none()
+                {
+                    return Ok(ReceiptOutcome::Replaced { nonce });
+                }
+
+                if missing_blocks >= DROPPED_AFTER_BLOCKS {
+                    return Ok(ReceiptOutcome::Dropped);
+                }
             }
 
-            let current_block = provider
-                .get_block_number()
-                .await?;
+            if head.saturating_sub(submitted_block) > max_blocks_wait {
+                return Ok(ReceiptOutcome::TimedOut);
+            }
 
-            if current_block.saturating_sub(submitted_block) > max_blocks_wait {
-                // tx is very likely stuck / underpriced
+            head = self.next_head(&mut heads).await?;
+        }
+    }
+
+    // New block numbers from `newHeads`, or None every `poll_interval`
+    // seconds. Polls with `eth_blockNumber` rather than a block filter, which
+    // many load-balanced HTTP RPCs do not support.
+    async fn new_heads(&self, poll_interval: u64) -> Result<BoxStream<'static, Option<u64>>> {
+        match self.contract.provider().subscribe_blocks().await {
+            Ok(sub) => return Ok(sub.into_stream().map(|header| Some(header.number)).boxed()),
+            Err(e) => log::debug!("newHeads subscription unavailable, polling: {e}"),
+        }
+
+        let interval = std::time::Duration::from_secs(poll_interval);
+
+        Ok(futures::stream::repeat(())
+            .then(move |_| tokio::time::sleep(interval))
+            .map(|_| None)
+            .boxed())
+    }
+
+    async fn next_head(&self, heads: &mut BoxStream<'static, Option<u64>>) -> Result<u64> {
+        match heads.next().await {
+            Some(Some(number)) => Ok(number),
+            Some(None) => Ok(self.contract.provider().get_block_number().await?),
+            None => anyhow::bail!("Block stream ended"),
+        }
+    }
+
+    /// Start tracking a broadcast so it can be sped up or cancelled
+    pub async fn replacement_group(&self, broadcast: &BroadcastedTransaction) -> Result<ReplacementGroup> {
+        let tx = self
+            .contract
+            .provider()
+            .get_transaction_by_hash(broadcast.hash)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Transaction {} not found", broadcast.hash))?;
+
+        Ok(ReplacementGroup {
+            from: tx.from(),
+            nonce: tx.nonce(),
+            hashes: vec![broadcast.hash],
+            submitted_block: broadcast.submitted_block,
+        })
+    }
+
+    /// Explain a reverted receipt: replays the transaction with `eth_call`
+    /// on its parent block and decodes the revert reason
+    pub async fn transaction_failure(&self, receipt: &TransactionReceipt) -> Result<TransactionFailure> {
+        let provider = self.contract.provider();
+        let hash = receipt.transaction_hash;
+
+        let fee_paid = U256::from(receipt.gas_used).saturating_mul(U256::from(receipt.effective_gas_price));
+
+        let tx = provider
+            .get_transaction_by_hash(hash)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Transaction {hash} not found"))?;
+        let gas_limit = ConsensusTransaction::gas_limit(&tx);
+
+        let mut request = tx.into_request();
+        // The nonce is spent by now, and fees only matter for inclusion
+        request.nonce = None;
+        request.gas_price = None;
+        request.max_fee_per_gas = None;
+        request.max_priority_fee_per_gas = None;
+
+        let block = receipt.block_number.map(|number| BlockId::number(number.saturating_sub(1)));
+
+        let mut reason = match provider.call(request).block(block.unwrap_or_default()).await {
+            Ok(_) => None,
+            Err(e) => RevertReason::from_rpc_error(&e),
+        };
+
+        if reason.is_none() && receipt.gas_used >= gas_limit {
+            reason = Some(RevertReason::Unknown("out of gas".to_string()));
+        }
+
+        let failure = TransactionFailure {
+            hash,
+            block_number: receipt.block_number,
+            gas_used: receipt.gas_used,
+            fee_paid,
+            reason,
+        };
+
+        log::warn!("{failure}");
+
+        Ok(failure)
+    }
+
+    /// Which hash of the group was mined, if any.
+    ///
+    /// Fails with `NonceConsumed` when the nonce was used by a transaction
+    /// outside the group.
+    pub async fn find_mined(&self, group: &ReplacementGroup) -> Result<Option<(TxHash, TransactionReceipt)>> {
+        if let Some(mined) = self.group_receipt(group).await? {
+            return Ok(Some(mined));
+        }
+
+        let mined_nonce = self.contract.provider().get_transaction_count(group.from).await?;
+        if mined_nonce > group.nonce {
+            // One of ours may have been mined since the receipt lookup
+            if let Some(mined) = self.group_receipt(group).await? {
+                return Ok(Some(mined));
+            }
+
+            return Err(NonceConsumed { from: group.from, nonce: group.nonce }.into());
+        }
+
+        Ok(None)
+    }
+
+    async fn group_receipt(&self, group: &ReplacementGroup) -> Result<Option<(TxHash, TransactionReceipt)>> {
+        for hash in group.hashes.iter().rev() {
+            if let Some(receipt) = self.contract.provider().get_transaction_receipt(*hash).await? {
+                return Ok(Some((*hash, receipt)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Like `wait_for_receipt`, for whichever hash of the group mines
+    pub async fn wait_for_group_receipt(
+        &self,
+        group: &ReplacementGroup,
+        max_blocks_wait: u64,
+        wait_time: u64,
+    ) -> Result<Option<(TxHash, TransactionReceipt)>> {
+        let mut heads = self.new_heads(wait_time).await?;
+        let mut current_block = self.contract.provider().get_block_number().await?;
+
+        loop {
+            if let Some((hash, receipt)) = self.find_mined(group).await? {
+                if !receipt.status() {
+                    return Err(self.transaction_failure(&receipt).await?.into());
+                }
+                return Ok(Some((hash, receipt)));
+            }
+
+            if current_block.saturating_sub(group.submitted_block) > max_blocks_wait {
                 return Ok(None);
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(wait_time)).await;
+            current_block = self.next_head(&mut heads).await?;
         }
     }
 
+    /// Send a transaction signed earlier (see `sign_transfer`).
+    ///
+    /// Resubmitting bytes the node already has is not an error.
+    pub async fn submit_raw(&self, raw: &[u8]) -> Result<BroadcastedTransaction> {
+        let provider = self.contract.provider();
+
+        let submitted_block = provider.get_block_number().await?;
+        let hash = submit_raw_to(provider, raw).await?;
+
+        Ok(BroadcastedTransaction {
+            hash,
+            submitted_block,
+        })
+    }
+
+    /// Export a prepared transfer from `from` for offline signing.
+    ///
+    /// Works with a read-only provider; the nonce comes from the nonce
+    /// manager when one is set.
+    pub async fn export_transfer(
+        &self,
+        from: Address,
+        to: Address,
+        amount_wei: U256,
+        prepared: &PreparedTransfer,
+    ) -> Result<UnsignedTransfer> {
+        let provider = self.contract.provider();
+
+        // Re-estimate as the real sender: the offline side cannot adjust gas
+        let call = self.contract.transfer(to, amount_wei).from(from);
+        let gas_limit = call.estimate_gas().await?;
+        self.simulate_token_call(call.into_transaction_request()).await?;
+
+        let nonce = match &self.nonce_manager {
+            Some(nonces) => nonces.allocate(provider, from).await?,
+            None => provider.get_transaction_count(from).pending().await?,
+        };
+
+        Ok(UnsignedTransfer {
+            chain_id: provider.get_chain_id().await?,
+            token: *self.contract.address(),
+            from,
+            to,
+            amount: amount_wei,
+            nonce,
+            gas_limit: gas_limit.max(prepared.gas_estimate),
+            fee_mode: prepared.fee_mode,
+            max_fee_per_gas: prepared.max_fee_per_gas.try_into()
+                .map_err(|_| anyhow::anyhow!("max_fee_per_gas overflowed u128"))?,
+            max_priority_fee_per_gas: prepared.max_priority_fee_per_gas.try_into()
+                .map_err(|_| anyhow::anyhow!("max_priority_fee_per_gas overflowed u128"))?,
+        })
+    }
+
+    /// Broadcast a transfer signed offline, after checking it is exactly
+    /// the `unsigned` transfer exported for this token
+    pub async fn broadcast_signed(
+        &self,
+        unsigned: &UnsignedTransfer,
+        signed: &SignedTransfer,
+    ) -> Result<BroadcastedTransaction> {
+        if unsigned.token != *self.contract.address() {
+            anyhow::bail!("Transfer was exported for token {}, not {}", unsigned.token, self.contract.address());
+        }
+
+        offline::verify_signed(signed, unsigned)?;
+
+        self.submit_raw(&signed.raw).await
+    }
+
     pub async fn get_latest_block(&self) -> Result<u64> {
         let block = self.contract.provider().get_block_number().await?;
         Ok(block)
     }
 }
+
+// Signing methods: only available with a wallet-backed provider
+impl TokenManager<AppProvider> {
+    pub async fn broadcast_transfer(
+        &self,
+        to: Address,
+        amount_wei: U256,
+        prepared: &PreparedTransfer,
+    ) -> Result<BroadcastedTransaction> {
+        let request = self.transfer_request(to, amount_wei);
+        self.simulate_token_call(request.clone()).await?;
+
+        self.send_request(with_fees(request, prepared)?).await
+    }
+
+    /// Send native coin prepared by `prepare_native_transfer` or
+    /// `prepare_native_sweep`. The gas limit is the prepared estimate, so a
+    /// sweep pays exactly the fee it was computed with.
+    pub async fn broadcast_native_transfer(
+        &self,
+        to: Address,
+        amount_wei: U256,
+        prepared: &PreparedTransfer,
+    ) -> Result<BroadcastedTransaction> {
+        let request = TransactionRequest::default()
+            .with_from(self.contract.provider().default_signer_address())
+            .with_to(to)
+            .with_value(amount_wei)
+            .with_gas_limit(prepared.gas_estimate);
+
+        self.send_request(with_fees(request, prepared)?).await
+    }
+
+    /// Set the allowance of `spender` to `amount_wei`
+    pub async fn approve(&self, spender: Address, amount_wei: U256) -> Result<BroadcastedTransaction> {
+        let request = self.contract.approve(spender, amount_wei).into_transaction_request();
+
+        self.send_token_call(request).await
+    }
+
+    /// `approve` for tokens like USDT that reject changing a non-zero
+    /// allowance: resets it to zero first and waits for that to be mined.
+    ///
+    /// Returns every transaction sent; empty if the allowance already matches.
+    pub async fn safe_approve(
+        &self,
+        spender: Address,
+        amount_wei: U256,
+        max_blocks_wait: u64,
+        wait_time: u64,
+    ) -> Result<Vec<BroadcastedTransaction>> {
+        let owner = self.contract.provider().default_signer_address();
+        let current = self.get_allowance(owner, spender).await?;
+
+        if current == amount_wei {
+            return Ok(Vec::new());
+        }
+
+        let mut sent = Vec::new();
+
+        if !current.is_zero() && !amount_wei.is_zero() {
+            let reset = self.approve(spender, U256::ZERO).await?;
+
+            self.wait_for_receipt(reset.hash, reset.submitted_block, max_blocks_wait, wait_time)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Allowance reset {} not mined", reset.hash))?;
+
+            sent.push(reset);
+        }
+
+        sent.push(self.approve(spender, amount_wei).await?);
+
+        Ok(sent)
+    }
+
+    /// Move `amount_wei` from `from` to `to` using this wallet's allowance
+    pub async fn transfer_from(&self, from: Address, to: Address, amount_wei: U256) -> Result<BroadcastedTransaction> {
+        let request = self.contract.transferFrom(from, to, amount_wei).into_transaction_request();
+
+        self.send_token_call(request).await
+    }
+
+    // `send_prepared` for calls returning an ERC-20 bool, checked first
+    pub(crate) async fn send_token_call(&self, request: TransactionRequest) -> Result<BroadcastedTransaction> {
+        let request = request.with_from(self.contract.provider().default_signer_address());
+        self.simulate_token_call(request.clone()).await?;
+
+        self.send_prepared(request).await
+    }
+
+    // Estimate as this wallet, then send with the estimated gas and fees
+    pub(crate) async fn send_prepared(&self, request: TransactionRequest) -> Result<BroadcastedTransaction> {
+        let request = request.with_from(self.contract.provider().default_signer_address());
+        let prepared = self.prepare_request(request.clone()).await?;
+
+        let request = with_fees(request.with_gas_limit(prepared.gas_estimate), &prepared)?;
+
+        self.send_request(request).await
+    }
+
+    // Sign and send, taking the nonce from the nonce manager when one is set
+    async fn send_request(&self, request: TransactionRequest) -> Result<BroadcastedTransaction> {
+        let provider = self.contract.provider();
+
+        let submitted_block = provider.get_block_number().await?;
+
+        let tx = match &self.nonce_manager {
+            None => provider.send_transaction(request).await?,
+            Some(nonces) => {
+                let sender = provider.default_signer_address();
+                let nonce = nonces.allocate(provider, sender).await?;
+
+                match provider.send_transaction(request.with_nonce(nonce)).await {
+                    Ok(tx) => tx,
+                    Err(e) => {
+                        let rejected = send_rejected(&e);
+                        let e = anyhow::Error::from(e);
+
+                        // Stale view: ask the node. A rejected send never used
+                        // the nonce; after a transport failure it may be live,
+                        // so it stays allocated rather than being reused.
+                        if nonce::is_nonce_error(&e) {
+                            nonces.resync(provider, sender).await?;
+                        } else if rejected {
+                            nonces.release(sender, nonce).await?;
+                        } else {
+                            log::warn!("nonce {nonce} of {sender} kept after a failed send; resync once its outcome is known");
+                        }
+
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        Ok(BroadcastedTransaction {
+            hash: *tx.tx_hash(),
+            submitted_block,
+        })
+    }
+
+    /// Build and sign a transfer without sending it.
+    ///
+    /// Submit the result with `submit_raw` (or `submit_raw_to` for other RPCs).
+    /// The signed nonce must then be submitted, or released to the nonce
+    /// manager if the transfer is dropped; otherwise later nonces stall.
+    /// Without a nonce manager the nonce is the node's pending count.
+    pub async fn sign_transfer(
+        &self,
+        to: Address,
+        amount_wei: U256,
+        prepared: &PreparedTransfer,
+    ) -> Result<SignedTransfer> {
+        let provider = self.contract.provider();
+        let sender = provider.default_signer_address();
+
+        let request = self.transfer_request(to, amount_wei);
+        self.simulate_token_call(request.clone()).await?;
+        let request = with_fees(request, prepared)?;
+
+        // Set explicitly: the provider's cached nonce filler would count
+        // this transfer as sent and leave a gap if it never is
+        let nonce = match &self.nonce_manager {
+            Some(nonces) => nonces.allocate(provider, sender).await?,
+            None => provider.get_transaction_count(sender).pending().await?,
+        };
+        let request = request.with_nonce(nonce);
+
+        match self.sign_request(request).await {
+            Ok(signed) => Ok(signed),
+            Err(e) => {
+                if let Some(nonces) = &self.nonce_manager {
+                    nonces.release(sender, nonce).await?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Fillers add whatever is missing (gas limit, nonce, chain id), then the wallet signs
+    async fn sign_request(&self, request: TransactionRequest) -> Result<SignedTransfer> {
+        let provider = self.contract.provider();
+
+        let envelope = provider
+            .fill(request)
+            .await?
+            .try_into_envelope()
+            .map_err(|e| anyhow::anyhow!("Transaction not ready for signing: {e}"))?;
+
+        Ok(SignedTransfer {
+            hash: *envelope.tx_hash(),
+            raw: Bytes::from(envelope.encoded_2718()),
+            from: provider.default_signer_address(),
+            nonce: ConsensusTransaction::nonce(&envelope),
+        })
+    }
+
+    // Transfer call from this wallet, without fees: simulated as is, since
+    // fees without a gas limit make the node check the balance against its
+    // gas cap times the fee
+    fn transfer_request(&self, to: Address, amount_wei: U256) -> TransactionRequest {
+        self.contract
+            .transfer(to, amount_wei)
+            .into_transaction_request()
+            .with_from(self.contract.provider().default_signer_address())
+    }
+
+    /// Re-send the latest transaction of `group` with the same nonce and fees
+    /// raised by `bump_percent` (at least `MIN_REPLACEMENT_BUMP_PERCENT`)
+    pub async fn speed_up(&self, group: &mut ReplacementGroup, bump_percent: u64) -> Result<TxHash> {
+        let latest = self.pending_replaceable(group).await?;
+        let request = self.replacement_request(group, &latest, speed_up_request(&latest), bump_percent).await?;
+
+        self.send_replacement(group, request).await
+    }
+
+    /// `speed_up` without sending: sign the replacement so it can be stored
+    /// before `submit_raw`. `group` is left unchanged; add the hash once saved.
+    pub async fn sign_speed_up(&self, group: &ReplacementGroup, bump_percent: u64) -> Result<SignedTransfer> {
+        let latest = self.pending_replaceable(group).await?;
+        let request = self.replacement_request(group, &latest, speed_up_request(&latest), bump_percent).await?;
+
+        self.sign_request(request).await
+    }
+
+    /// Replace the latest transaction of `group` with a zero-value self-transfer
+    pub async fn cancel(&self, group: &mut ReplacementGroup, bump_percent: u64) -> Result<TxHash> {
+        let latest = self.pending_replaceable(group).await?;
+
+        let request = TransactionRequest::default()
+            .with_to(group.from)
+            .with_value(U256::ZERO)
+            .with_gas_limit(21_000);
+        let request = self.replacement_request(group, &latest, request, bump_percent).await?;
+
+        self.send_replacement(group, request).await
+    }
+
+    // Latest transaction of the group, refusing if anything in the group was mined
+    async fn pending_replaceable(&self, group: &ReplacementGroup) -> Result<alloy::rpc::types::Transaction> {
+        if let Some((hash, _)) = self.find_mined(group).await? {
+            anyhow::bail!("Transaction {hash} is already mined");
+        }
+
+        let latest = group.latest().ok_or_else(|| anyhow::anyhow!("Replacement group is empty"))?;
+
+        self.contract
+            .provider()
+            .get_transaction_by_hash(latest)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Transaction {latest} dropped from the mempool"))
+    }
+
+    // `request` at the group's nonce with fees high enough to replace `latest`
+    async fn replacement_request(
+        &self,
+        group: &ReplacementGroup,
+        latest: &alloy::rpc::types::Transaction,
+        request: TransactionRequest,
+        bump_percent: u64,
+    ) -> Result<TransactionRequest> {
+        let bump_percent = bump_percent.max(MIN_REPLACEMENT_BUMP_PERCENT);
+        let (mode, max_fee, priority_fee, _) = self.estimate_fees().await?;
+
+        let request = request.with_from(group.from).with_nonce(group.nonce);
+
+        // Bumped old fees, or the current market if that is higher
+        let request = if latest.is_dynamic_fee() {
+            let old_priority = latest.max_priority_fee_per_gas().unwrap_or_default();
+            let priority = bump_fee(old_priority, bump_percent).max(priority_fee.saturating_to());
+            let max_fee = bump_fee(ConsensusTransaction::max_fee_per_gas(latest), bump_percent).max(max_fee.saturating_to()).max(priority);
+
+            request.with_max_fee_per_gas(max_fee).with_max_priority_fee_per_gas(priority)
+        } else {
+            let current = match mode {
+                FeeMode::Legacy => max_fee.saturating_to(),
+                FeeMode::Eip1559 => 0,
+            };
+            let gas_price = bump_fee(ConsensusTransaction::gas_price(latest).unwrap_or_default(), bump_percent).max(current);
+
+            request.with_gas_price(gas_price)
+        };
+
+        Ok(request)
+    }
+
+    async fn send_replacement(&self, group: &mut ReplacementGroup, request: TransactionRequest) -> Result<TxHash> {
+        let pending = self.contract.provider().send_transaction(request).await?;
+        let hash = *pending.tx_hash();
+
+        log::debug!("replaced nonce {} with {hash}", group.nonce);
+        group.hashes.push(hash);
+
+        Ok(hash)
+    }
+}
+
+// Same call as `latest`; fees are set by `replacement_request`
+fn speed_up_request(latest: &alloy::rpc::types::Transaction) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_kind(latest.kind())
+        .with_value(latest.value())
+        .with_input(latest.input().clone())
+        .with_gas_limit(latest.gas_limit())
+}
+
+// fee * (100 + percent) / 100, rounded up so the node's minimum is always met
+fn bump_fee(fee: u128, percent: u64) -> u128 {
+    let bumped = fee.saturating_mul(100 + percent as u128);
+    bumped.div_ceil(100)
+}
+
+// Surface reverts as a typed `RevertReason`
+fn call_error(error: TransportError) -> anyhow::Error {
+    match RevertReason::from_rpc_error(&error) {
+        Some(reason) => reason.into(),
+        None => error.into(),
+    }
+}
+
+// True when the transaction certainly did not reach the mempool: the node
+// answered with an error, or it failed before anything was sent
+fn send_rejected(error: &TransportError) -> bool {
+    matches!(
+        error,
+        RpcError::ErrorResp(_) | RpcError::LocalUsageError(_) | RpcError::SerError(_) | RpcError::UnsupportedFeature(_)
+    )
+}
+
+// bytes32 metadata is a NUL-padded string
+fn bytes32_to_string(value: B256) -> String {
+    let end = value.iter().position(|b| *b == 0).unwrap_or(32);
+
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+fn with_fees(request: TransactionRequest, prepared: &PreparedTransfer) -> Result<TransactionRequest> {
+    let max_fee_u128 = prepared.max_fee_per_gas.try_into()
+        .map_err(|_| anyhow::anyhow!("max_fee_per_gas overflowed u128"))?;
+    let max_priority_u128 = prepared.max_priority_fee_per_gas.try_into()
+        .map_err(|_| anyhow::anyhow!("max_priority_fee_per_gas overflowed u128"))?;
+
+    let request = match prepared.fee_mode {
+        FeeMode::Eip1559 => request
+            .with_max_fee_per_gas(max_fee_u128)
+            .with_max_priority_fee_per_gas(max_priority_u128),
+        // Setting gas_price makes the fillers build a type-0 transaction
+        FeeMode::Legacy => request.with_gas_price(max_fee_u128),
+    };
+
+    Ok(request)
+}
+
+/// Submit signed bytes to one RPC; "already known" counts as success.
+///
+/// Call it for several providers to rebroadcast the same transaction widely.
+pub async fn submit_raw_to<Q: Provider>(provider: &Q, raw: &[u8]) -> Result<TxHash> {
+    match provider.send_raw_transaction(raw).await {
+        Ok(pending) => Ok(*pending.tx_hash()),
+        Err(e) if e.to_string().to_lowercase().contains("already known") => {
+            // The hash of a signed transaction is the keccak of its 2718 encoding
+            Ok(keccak256(raw))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
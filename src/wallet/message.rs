@@ -0,0 +1,63 @@
+use alloy::dyn_abi::TypedData;
+use alloy::primitives::{Address, B256, Signature};
+use alloy::signers::Signer;
+use alloy::sol_types::{Eip712Domain, SolStruct};
+use anyhow::Result;
+use serde::Serialize;
+
+/// EIP-191 `personal_sign` of `message`
+pub async fn sign_personal<S: Signer + Sync>(signer: &S, message: &[u8]) -> Result<Signature> {
+    Ok(signer.sign_message(message).await?)
+}
+
+pub fn recover_personal(message: &[u8], signature: &Signature) -> Result<Address> {
+    Ok(signature.recover_address_from_msg(message)?)
+}
+
+/// True when `signature` over `message` was made by `expected`
+pub fn verify_personal(message: &[u8], signature: &Signature, expected: Address) -> Result<bool> {
+    Ok(recover_personal(message, signature)? == expected)
+}
+
+/// EIP-712 signature of a `sol!` struct under `domain`.
+///
+/// Sent as typed data, so an external signer sees the struct it signs.
+pub async fn sign_typed<S, T>(signer: &S, data: &T, domain: &Eip712Domain) -> Result<Signature>
+where
+    S: Signer + Sync,
+    T: SolStruct + Serialize,
+{
+    sign_typed_json(signer, &TypedData::from_struct(data, Some(domain.clone()))).await
+}
+
+pub fn recover_typed<T: SolStruct>(data: &T, domain: &Eip712Domain, signature: &Signature) -> Result<Address> {
+    Ok(signature.recover_address_from_prehash(&data.eip712_signing_hash(domain))?)
+}
+
+pub fn verify_typed<T: SolStruct>(
+    data: &T,
+    domain: &Eip712Domain,
+    signature: &Signature,
+    expected: Address,
+) -> Result<bool> {
+    Ok(recover_typed(data, domain, signature)? == expected)
+}
+
+/// EIP-712 signature of JSON typed data (`eth_signTypedData_v4` payload)
+pub async fn sign_typed_json<S: Signer + Sync>(signer: &S, typed: &TypedData) -> Result<Signature> {
+    Ok(signer.sign_dynamic_typed_data(typed).await?)
+}
+
+pub fn recover_typed_json(typed: &TypedData, signature: &Signature) -> Result<Address> {
+    Ok(signature.recover_address_from_prehash(&typed_json_hash(typed)?)?)
+}
+
+pub fn verify_typed_json(typed: &TypedData, signature: &Signature, expected: Address) -> Result<bool> {
+    Ok(recover_typed_json(typed, signature)? == expected)
+}
+
+fn typed_json_hash(typed: &TypedData) -> Result<B256> {
+    typed
+        .eip712_signing_hash()
+        .map_err(|e| anyhow::anyhow!("Invalid typed data: {e}"))
+}
@@ -0,0 +1,309 @@
+use std::fmt;
+
+use alloy::signers::local::coins_bip39::{English, Entropy, Mnemonic, Wordlist};
+use zeroize::Zeroizing;
+
+// Substitutions further than this from the typed word are not offered as hints
+const MAX_HINT_DISTANCE: usize = 2;
+
+/// BIP-39 phrase lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordCount {
+    Twelve,
+    Fifteen,
+    Eighteen,
+    TwentyOne,
+    TwentyFour,
+}
+
+impl WordCount {
+    pub const ALL: [WordCount; 5] = [
+        WordCount::Twelve,
+        WordCount::Fifteen,
+        WordCount::Eighteen,
+        WordCount::TwentyOne,
+        WordCount::TwentyFour,
+    ];
+
+    pub fn words(self) -> usize {
+        match self {
+            WordCount::Twelve => 12,
+            WordCount::Fifteen => 15,
+            WordCount::Eighteen => 18,
+            WordCount::TwentyOne => 21,
+            WordCount::TwentyFour => 24,
+        }
+    }
+
+    /// Entropy strength in bits (the rest of the phrase is checksum)
+    pub fn entropy_bits(self) -> usize {
+        self.words() * 11 * 32 / 33
+    }
+}
+
+impl TryFrom<usize> for WordCount {
+    type Error = MnemonicValidationError;
+
+    fn try_from(count: usize) -> Result<Self, Self::Error> {
+        WordCount::ALL
+            .into_iter()
+            .find(|wc| wc.words() == count)
+            .ok_or(MnemonicValidationError::InvalidWordCount(count))
+    }
+}
+
+/// Single-word replacement that would make a phrase valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordFix {
+    /// Zero-based position of the word in the phrase
+    pub position: usize,
+    pub replacement: &'static str,
+}
+
+/// Why a phrase was rejected. Messages are safe to show to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicValidationError {
+    /// Phrase is not 12, 15, 18, 21 or 24 words long
+    InvalidWordCount(usize),
+    /// Word is not in the English BIP-39 wordlist
+    UnknownWord {
+        position: usize,
+        word: String,
+        suggestion: Option<&'static str>,
+    },
+    /// All words are known but the checksum does not match;
+    /// `hints` lists close single-word substitutions that fix it
+    InvalidChecksum { hints: Vec<WordFix> },
+}
+
+impl fmt::Display for MnemonicValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MnemonicValidationError::InvalidWordCount(count) => {
+                write!(f, "expected 12, 15, 18, 21 or 24 words, found {count}")
+            }
+            MnemonicValidationError::UnknownWord { position, word, suggestion } => {
+                write!(f, "word #{} `{word}` is not in the wordlist", position + 1)?;
+                if let Some(s) = suggestion {
+                    write!(f, " (did you mean `{s}`?)")?;
+                }
+                Ok(())
+            }
+            MnemonicValidationError::InvalidChecksum { hints } => {
+                write!(f, "invalid checksum")?;
+                if let Some(fix) = hints.first() {
+                    write!(f, " (word #{} may be `{}`)", fix.position + 1, fix.replacement)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MnemonicValidationError {}
+
+/// Lowercase the phrase and collapse whitespace to single spaces
+pub fn normalize_phrase(phrase: &str) -> Zeroizing<String> {
+    let mut normalized = Zeroizing::new(String::with_capacity(phrase.len()));
+
+    for word in phrase.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.extend(word.chars().flat_map(char::to_lowercase));
+    }
+
+    normalized
+}
+
+/// Check word count, wordlist membership and checksum.
+pub fn validate_phrase(phrase: &str) -> Result<WordCount, MnemonicValidationError> {
+    let normalized = normalize_phrase(phrase);
+    let words: Vec<&str> = normalized.split(' ').filter(|w| !w.is_empty()).collect();
+
+    let count = WordCount::try_from(words.len())?;
+
+    for (position, word) in words.iter().enumerate() {
+        if English::get_index(word).is_err() {
+            return Err(MnemonicValidationError::UnknownWord {
+                position,
+                word: word.to_string(),
+                suggestion: closest_word(word),
+            });
+        }
+    }
+
+    if !checksum_ok(&words) {
+        return Err(MnemonicValidationError::InvalidChecksum { hints: checksum_hints(&words) });
+    }
+
+    Ok(count)
+}
+
+/// Recover the raw entropy behind a valid phrase (checksum bits dropped).
+pub fn phrase_to_entropy(phrase: &str) -> Result<Zeroizing<Vec<u8>>, MnemonicValidationError> {
+    let count = validate_phrase(phrase)?;
+    let normalized = normalize_phrase(phrase);
+
+    let mut entropy = Zeroizing::new(vec![0u8; count.entropy_bits() / 8]);
+    let mut bit = 0;
+
+    for word in normalized.split(' ') {
+        // Wordlist membership was checked by validate_phrase
+        let index = English::get_index(word).unwrap_or_default();
+
+        for shift in (0..11).rev() {
+            if bit < count.entropy_bits() && (index >> shift) & 1 == 1 {
+                entropy[bit / 8] |= 0x80 >> (bit % 8);
+            }
+            bit += 1;
+        }
+    }
+
+    Ok(entropy)
+}
+
+/// Build the phrase for 16, 20, 24, 28 or 32 bytes of entropy
+pub fn entropy_to_phrase(entropy: &[u8]) -> Option<Zeroizing<String>> {
+    let entropy = Entropy::from_slice(entropy).ok()?;
+    Some(Zeroizing::new(Mnemonic::<English>::new_from_entropy(entropy).to_phrase()))
+}
+
+/// Closest wordlist entry: a unique 4-letter prefix match wins (BIP-39 words
+/// are unique in their first four letters), otherwise the smallest edit distance.
+pub fn closest_word(word: &str) -> Option<&'static str> {
+    let list = English::get_all();
+
+    if word.chars().count() >= 4 {
+        let prefix: String = word.chars().take(4).collect();
+        if let Some(hit) = list.iter().find(|w| w.starts_with(prefix.as_str())) {
+            return Some(hit);
+        }
+    }
+
+    list.iter()
+        .map(|w| (edit_distance(word, w), *w))
+        .filter(|(d, _)| *d <= MAX_HINT_DISTANCE)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, w)| w)
+}
+
+fn checksum_ok(words: &[&str]) -> bool {
+    let phrase = Zeroizing::new(words.join(" "));
+    Mnemonic::<English>::new_from_phrase(&phrase).is_ok()
+}
+
+// Try every close substitution of every word; the nearest fixes come first
+fn checksum_hints(words: &[&str]) -> Vec<WordFix> {
+    let mut hints: Vec<(usize, WordFix)> = Vec::new();
+    let mut candidate: Vec<&str> = words.to_vec();
+
+    for (position, word) in words.iter().enumerate() {
+        for replacement in English::get_all() {
+            let distance = edit_distance(word, replacement);
+            if distance == 0 || distance > MAX_HINT_DISTANCE {
+                continue;
+            }
+
+            candidate[position] = replacement;
+            if checksum_ok(&candidate) {
+                hints.push((distance, WordFix { position, replacement }));
+            }
+        }
+        candidate[position] = word;
+    }
+
+    hints.sort_by_key(|(d, fix)| (*d, fix.position));
+    hints.into_iter().map(|(_, fix)| fix).collect()
+}
+
+// Levenshtein distance over chars
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "abandon" x11 + "about": all-zero 128-bit entropy
+    fn zero_phrase(last: &str) -> String {
+        format!("{}{last}", "abandon ".repeat(11))
+    }
+
+    #[test]
+    fn rejects_bad_word_count() {
+        assert_eq!(validate_phrase("abandon abandon about"), Err(MnemonicValidationError::InvalidWordCount(3)));
+        assert_eq!(validate_phrase("   "), Err(MnemonicValidationError::InvalidWordCount(0)));
+        assert_eq!(
+            validate_phrase(&format!("{} abandon", zero_phrase("about"))),
+            Err(MnemonicValidationError::InvalidWordCount(13))
+        );
+    }
+
+    #[test]
+    fn unknown_word_with_suggestion() {
+        let err = validate_phrase(&zero_phrase("abaut")).unwrap_err();
+        assert_eq!(
+            err,
+            MnemonicValidationError::UnknownWord { position: 11, word: "abaut".to_string(), suggestion: Some("about") }
+        );
+        assert_eq!(err.to_string(), "word #12 `abaut` is not in the wordlist (did you mean `about`?)");
+
+        // Unique four-letter prefix
+        assert_eq!(closest_word("abandonn"), Some("abandon"));
+        // Nothing close enough
+        assert_eq!(closest_word("qqqqqqqq"), None);
+    }
+
+    #[test]
+    fn checksum_typo_hints_the_fix() {
+        let phrase = zero_phrase("above");
+        let err = validate_phrase(&phrase).unwrap_err();
+
+        let MnemonicValidationError::InvalidChecksum { hints } = err else {
+            panic!("expected a checksum error, got {err:?}");
+        };
+        assert!(hints.contains(&WordFix { position: 11, replacement: "about" }), "{hints:?}");
+
+        // Every hint really fixes the phrase
+        let words: Vec<&str> = phrase.split(' ').collect();
+        for fix in &hints {
+            let mut fixed = words.clone();
+            fixed[fix.position] = fix.replacement;
+            assert!(checksum_ok(&fixed), "{fix:?}");
+        }
+    }
+
+    #[test]
+    fn accepts_every_word_count() {
+        for count in WordCount::ALL {
+            let entropy: Vec<u8> = (0..count.entropy_bits() / 8).map(|i| (i as u8).wrapping_mul(13)).collect();
+            let phrase = entropy_to_phrase(&entropy).unwrap();
+
+            assert_eq!(validate_phrase(&phrase), Ok(count));
+            assert_eq!(*phrase_to_entropy(&phrase).unwrap(), entropy);
+        }
+    }
+
+    #[test]
+    fn normalizes_case_and_whitespace() {
+        let phrase = format!("  {}\tABOUT ", "Abandon  ".repeat(11));
+
+        assert_eq!(validate_phrase(&phrase), Ok(WordCount::Twelve));
+        assert_eq!(*normalize_phrase(&phrase), zero_phrase("about"));
+    }
+}
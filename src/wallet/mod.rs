@@ -4,6 +4,13 @@ use alloy::signers::local::coins_bip39::{English, Mnemonic};
 use rand::rngs::OsRng;
 use zeroize::Zeroizing;
 
+pub mod message;
+pub mod mnemonic;
+pub mod shamir;
+
+pub use mnemonic::{MnemonicValidationError, WordCount, WordFix};
+pub use shamir::{MnemonicShare, ShamirError};
+
 pub struct Wallet;
 
 impl Wallet {
@@ -25,6 +32,38 @@ impl Wallet {
         Ok((phrase, signer))
     }
 
+    /// Generate a wallet with one of the BIP-39 phrase lengths.
+    pub fn generate_wallet_with_count(
+        count: WordCount,
+        index: u32,
+        password: Option<&str>,
+    ) -> Result<(Zeroizing<String>, PrivateKeySigner), LocalSignerError>
+    {
+        Self::generate_wallet(count.words(), index, password)
+    }
+
+    /// Check a user-supplied phrase before building a signer from it.
+    ///
+    /// Unlike `build_signer`, the error tells which word is wrong and,
+    /// where possible, the closest wordlist match.
+    pub fn validate_mnemonic(phrase: &str) -> Result<WordCount, MnemonicValidationError> {
+        mnemonic::validate_phrase(phrase)
+    }
+
+    /// Split a phrase into `shares` Shamir shares for custodial backup;
+    /// any `threshold` of them recover it via `combine_mnemonic_shares`.
+    pub fn split_mnemonic(
+        phrase: &Zeroizing<String>,
+        threshold: u8,
+        shares: u8,
+    ) -> Result<Vec<MnemonicShare>, ShamirError> {
+        shamir::split_mnemonic(phrase, threshold, shares)
+    }
+
+    pub fn combine_mnemonic_shares(shares: &[MnemonicShare]) -> Result<Zeroizing<String>, ShamirError> {
+        shamir::combine_mnemonic(shares)
+    }
+
     fn generate_mnemonic(size: usize) -> Result<Zeroizing<String>, LocalSignerError> 
     {
         // Direct OS entropy (no userland state, no PRNG, no counter limits)
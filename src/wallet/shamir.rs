@@ -0,0 +1,373 @@
+use std::fmt;
+
+use alloy::hex;
+use alloy::primitives::keccak256;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::mnemonic::{self, MnemonicValidationError};
+
+// Encoded share layout: version | group id (2) | threshold | index | value
+const SHARE_VERSION: u8 = 1;
+const SHARE_HEADER_LEN: usize = 5;
+// Bytes of keccak256(entropy) appended before splitting, checked on recombine
+const DIGEST_LEN: usize = 4;
+
+/// One M-of-N share of a mnemonic's entropy (Shamir over GF(256)).
+#[derive(Clone, PartialEq, Eq)]
+pub struct MnemonicShare {
+    /// Random id shared by all shares of one split
+    pub group_id: u16,
+    pub threshold: u8,
+    /// x-coordinate of the share, 1..=N
+    pub index: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+// Never print share bytes
+impl fmt::Debug for MnemonicShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MnemonicShare")
+            .field("group_id", &self.group_id)
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MnemonicShare {
+    /// Hex encoding for backup (auto-zeroized when dropped)
+    pub fn encode(&self) -> Zeroizing<String> {
+        let mut raw = Zeroizing::new(Vec::with_capacity(SHARE_HEADER_LEN + self.value.len()));
+        raw.push(SHARE_VERSION);
+        raw.extend_from_slice(&self.group_id.to_be_bytes());
+        raw.push(self.threshold);
+        raw.push(self.index);
+        raw.extend_from_slice(&self.value);
+
+        Zeroizing::new(hex::encode(raw.as_slice()))
+    }
+
+    pub fn decode(encoded: &str) -> Result<Self, ShamirError> {
+        let raw = Zeroizing::new(
+            hex::decode(encoded.trim()).map_err(|_| ShamirError::MalformedShare)?,
+        );
+
+        if raw.len() <= SHARE_HEADER_LEN + DIGEST_LEN || raw[0] != SHARE_VERSION {
+            return Err(ShamirError::MalformedShare);
+        }
+
+        let share = Self {
+            group_id: u16::from_be_bytes([raw[1], raw[2]]),
+            threshold: raw[3],
+            index: raw[4],
+            value: Zeroizing::new(raw[SHARE_HEADER_LEN..].to_vec()),
+        };
+
+        if share.index == 0 || share.threshold == 0 {
+            return Err(ShamirError::MalformedShare);
+        }
+
+        Ok(share)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShamirError {
+    /// Threshold must be 1..=shares and shares at most 255
+    InvalidThreshold { threshold: u8, shares: u8 },
+    NotEnoughShares { threshold: u8, provided: usize },
+    /// Shares come from different splits or have different lengths
+    MismatchedShares,
+    DuplicateShare(u8),
+    MalformedShare,
+    /// Recombined secret failed its digest: a share is corrupted or foreign
+    DigestMismatch,
+    Mnemonic(MnemonicValidationError),
+}
+
+impl fmt::Display for ShamirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShamirError::InvalidThreshold { threshold, shares } => {
+                write!(f, "invalid threshold {threshold} for {shares} shares")
+            }
+            ShamirError::NotEnoughShares { threshold, provided } => {
+                write!(f, "need {threshold} shares, got {provided}")
+            }
+            ShamirError::MismatchedShares => write!(f, "shares belong to different secrets"),
+            ShamirError::DuplicateShare(index) => write!(f, "share #{index} provided twice"),
+            ShamirError::MalformedShare => write!(f, "malformed share"),
+            ShamirError::DigestMismatch => write!(f, "recovered secret failed its digest check"),
+            ShamirError::Mnemonic(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ShamirError {}
+
+impl From<MnemonicValidationError> for ShamirError {
+    fn from(e: MnemonicValidationError) -> Self {
+        ShamirError::Mnemonic(e)
+    }
+}
+
+/// Split a mnemonic into `shares` shares, any `threshold` of which recover it.
+pub fn split_mnemonic(
+    phrase: &Zeroizing<String>,
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<MnemonicShare>, ShamirError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ShamirError::InvalidThreshold { threshold, shares });
+    }
+
+    let entropy = mnemonic::phrase_to_entropy(phrase)?;
+
+    // Sized up front so the buffer never reallocates and leaves an unzeroized copy
+    let mut secret = Zeroizing::new(Vec::with_capacity(entropy.len() + DIGEST_LEN));
+    secret.extend_from_slice(&entropy);
+    secret.extend_from_slice(&keccak256(entropy.as_slice())[..DIGEST_LEN]);
+
+    let group_id = OsRng.next_u32() as u16;
+    let mut result: Vec<MnemonicShare> = (1..=shares)
+        .map(|index| MnemonicShare {
+            group_id,
+            threshold,
+            index,
+            value: Zeroizing::new(Vec::with_capacity(secret.len())),
+        })
+        .collect();
+
+    // One random polynomial per secret byte; coefficient 0 is the byte itself
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for &byte in secret.iter() {
+        coefficients[0] = byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+
+        for share in result.iter_mut() {
+            share.value.push(evaluate(&coefficients, share.index));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Recover the mnemonic from at least `threshold` shares of the same split.
+pub fn combine_mnemonic(shares: &[MnemonicShare]) -> Result<Zeroizing<String>, ShamirError> {
+    let first = shares.first().ok_or(ShamirError::NotEnoughShares { threshold: 1, provided: 0 })?;
+
+    if shares.len() < first.threshold as usize {
+        return Err(ShamirError::NotEnoughShares {
+            threshold: first.threshold,
+            provided: shares.len(),
+        });
+    }
+
+    for (i, share) in shares.iter().enumerate() {
+        if share.group_id != first.group_id
+            || share.threshold != first.threshold
+            || share.value.len() != first.value.len()
+        {
+            return Err(ShamirError::MismatchedShares);
+        }
+        if shares[..i].iter().any(|s| s.index == share.index) {
+            return Err(ShamirError::DuplicateShare(share.index));
+        }
+    }
+
+    // Exactly `threshold` points define the polynomial
+    let used = &shares[..first.threshold as usize];
+
+    let mut secret = Zeroizing::new(vec![0u8; first.value.len()]);
+    for (i, share) in used.iter().enumerate() {
+        let weight = lagrange_at_zero(used, i);
+        for (out, &y) in secret.iter_mut().zip(share.value.iter()) {
+            *out ^= gf_mul(y, weight);
+        }
+    }
+
+    let (entropy, digest) = secret.split_at(secret.len() - DIGEST_LEN);
+    if keccak256(entropy)[..DIGEST_LEN] != *digest {
+        return Err(ShamirError::DigestMismatch);
+    }
+
+    mnemonic::entropy_to_phrase(entropy).ok_or(ShamirError::MalformedShare)
+}
+
+// Horner's rule in GF(256)
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+    coefficients.iter().rev().fold(0, |acc, &c| gf_mul(acc, x) ^ c)
+}
+
+// Lagrange basis polynomial i evaluated at x = 0
+fn lagrange_at_zero(shares: &[MnemonicShare], i: usize) -> u8 {
+    let xi = shares[i].index;
+
+    shares
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j != i)
+        .fold(1, |acc, (_, share)| {
+            // (0 - xj) / (xi - xj); subtraction is XOR in GF(256)
+            gf_mul(acc, gf_mul(share.index, gf_inv(xi ^ share.index)))
+        })
+}
+
+// Multiplication modulo the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 == a^-1 for non-zero a
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp != 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::WordCount;
+
+    fn phrase(count: WordCount) -> Zeroizing<String> {
+        let entropy: Vec<u8> = (0..count.entropy_bits() / 8).map(|i| (i as u8).wrapping_mul(37) ^ 0xa5).collect();
+        mnemonic::entropy_to_phrase(&entropy).unwrap()
+    }
+
+    // Every k-element subset of 0..n, in index order
+    fn subsets(n: usize, k: usize) -> Vec<Vec<usize>> {
+        (0u32..1 << n)
+            .filter(|mask| mask.count_ones() as usize == k)
+            .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_every_word_count_and_subset() {
+        for count in WordCount::ALL {
+            let phrase = phrase(count);
+            let shares = split_mnemonic(&phrase, 3, 5).unwrap();
+            assert_eq!(shares.len(), 5);
+
+            for k in 3..=5 {
+                for subset in subsets(5, k) {
+                    let picked: Vec<MnemonicShare> = subset.iter().rev().map(|&i| shares[i].clone()).collect();
+                    let recovered = combine_mnemonic(&picked).unwrap();
+                    assert_eq!(*recovered, *phrase, "{count:?} shares {subset:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_of_one_and_n_of_n() {
+        let phrase = phrase(WordCount::Twelve);
+
+        let single = split_mnemonic(&phrase, 1, 1).unwrap();
+        assert_eq!(*combine_mnemonic(&single).unwrap(), *phrase);
+
+        let all = split_mnemonic(&phrase, 4, 4).unwrap();
+        assert_eq!(*combine_mnemonic(&all).unwrap(), *phrase);
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let phrase = phrase(WordCount::TwentyFour);
+        let shares = split_mnemonic(&phrase, 2, 3).unwrap();
+
+        let decoded: Vec<MnemonicShare> = shares
+            .iter()
+            .map(|share| MnemonicShare::decode(&share.encode()).unwrap())
+            .collect();
+        assert_eq!(decoded, shares);
+
+        assert_eq!(*combine_mnemonic(&decoded[1..]).unwrap(), *phrase);
+    }
+
+    #[test]
+    fn decode_rejects_malformed() {
+        let share = split_mnemonic(&phrase(WordCount::Twelve), 2, 2).unwrap().remove(0);
+        let encoded = share.encode();
+
+        assert_eq!(MnemonicShare::decode("zz"), Err(ShamirError::MalformedShare));
+        assert_eq!(MnemonicShare::decode(&encoded[..12]), Err(ShamirError::MalformedShare));
+        // Unknown version
+        assert_eq!(MnemonicShare::decode(&format!("02{}", &encoded[2..])), Err(ShamirError::MalformedShare));
+        // Index 0 is the secret itself
+        assert_eq!(
+            MnemonicShare::decode(&format!("{}00{}", &encoded[..8], &encoded[10..])),
+            Err(ShamirError::MalformedShare)
+        );
+    }
+
+    #[test]
+    fn tampered_share_fails_digest() {
+        let phrase = phrase(WordCount::Eighteen);
+        let mut shares = split_mnemonic(&phrase, 3, 5).unwrap();
+
+        shares[1].value[0] ^= 0x01;
+
+        assert_eq!(combine_mnemonic(&shares[..3]), Err(ShamirError::DigestMismatch));
+        // Shares without the tampered one still recover
+        assert_eq!(*combine_mnemonic(&shares[2..]).unwrap(), *phrase);
+    }
+
+    #[test]
+    fn rejects_duplicate_and_too_few_shares() {
+        let shares = split_mnemonic(&phrase(WordCount::Fifteen), 3, 5).unwrap();
+
+        let duplicated = vec![shares[0].clone(), shares[2].clone(), shares[0].clone()];
+        assert_eq!(combine_mnemonic(&duplicated), Err(ShamirError::DuplicateShare(shares[0].index)));
+
+        assert_eq!(
+            combine_mnemonic(&shares[..2]),
+            Err(ShamirError::NotEnoughShares { threshold: 3, provided: 2 })
+        );
+        assert_eq!(
+            combine_mnemonic(&[]),
+            Err(ShamirError::NotEnoughShares { threshold: 1, provided: 0 })
+        );
+    }
+
+    #[test]
+    fn rejects_shares_of_different_splits() {
+        let phrase = phrase(WordCount::Twelve);
+        let a = split_mnemonic(&phrase, 2, 3).unwrap();
+        let mut b = split_mnemonic(&phrase, 2, 3).unwrap();
+        // Group ids are random 16-bit values and may coincide
+        b[1].group_id = a[0].group_id.wrapping_add(1);
+
+        assert_eq!(combine_mnemonic(&[a[0].clone(), b[1].clone()]), Err(ShamirError::MismatchedShares));
+    }
+
+    #[test]
+    fn rejects_invalid_threshold() {
+        let phrase = phrase(WordCount::Twelve);
+
+        assert_eq!(split_mnemonic(&phrase, 0, 3), Err(ShamirError::InvalidThreshold { threshold: 0, shares: 3 }));
+        assert_eq!(split_mnemonic(&phrase, 4, 3), Err(ShamirError::InvalidThreshold { threshold: 4, shares: 3 }));
+    }
+}
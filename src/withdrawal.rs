@@ -0,0 +1,558 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::{Address, Bytes, TxHash, U256};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{NonceConsumed, ReplacementGroup};
+use crate::token::TokenManager;
+
+/// A `Signed` claim without a stored transaction older than this is taken
+/// to belong to a crashed process and is returned to `Queued`
+pub const CLAIM_TIMEOUT_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WithdrawalStatus {
+    Queued,
+    /// Claimed for sending; once `raw_tx` is set the transaction may
+    /// already be on the network. Without `raw_tx` nothing was sent.
+    Signed,
+    Broadcast,
+    /// Sped up or cancelled; `hashes` holds every same-nonce transaction
+    Replaced,
+    Mined,
+    Failed,
+}
+
+impl WithdrawalStatus {
+    pub fn is_final(self) -> bool {
+        matches!(self, WithdrawalStatus::Mined | WithdrawalStatus::Failed)
+    }
+}
+
+/// One payout, identified by the caller's idempotency key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalRecord {
+    pub key: String,
+    pub to: Address,
+    pub amount: U256,
+    pub status: WithdrawalStatus,
+    /// Original hash first, replacements after it
+    pub hashes: Vec<TxHash>,
+    pub from: Option<Address>,
+    pub nonce: Option<u64>,
+    pub submitted_block: Option<u64>,
+    /// Hash that was mined, once known
+    pub mined_hash: Option<TxHash>,
+    /// Amount the recipient got per the receipt's logs; below `amount`
+    /// for fee-on-transfer tokens
+    #[serde(default)]
+    pub received: Option<U256>,
+    pub error: Option<String>,
+    /// Latest signed transaction, stored before it is sent so it can be rebroadcast
+    #[serde(default)]
+    pub raw_tx: Option<Bytes>,
+    /// UNIX seconds of the last transition
+    pub updated_at: u64,
+}
+
+impl WithdrawalRecord {
+    fn new(key: &str, to: Address, amount: U256) -> Self {
+        Self {
+            key: key.to_string(),
+            to,
+            amount,
+            status: WithdrawalStatus::Queued,
+            hashes: Vec::new(),
+            from: None,
+            nonce: None,
+            submitted_block: None,
+            mined_hash: None,
+            received: None,
+            error: None,
+            raw_tx: None,
+            updated_at: now(),
+        }
+    }
+
+    // `submitted_block` is unknown while `Signed`; lookups do not need it
+    fn replacement_group(&self) -> Option<ReplacementGroup> {
+        if self.hashes.is_empty() {
+            return None;
+        }
+
+        Some(ReplacementGroup {
+            from: self.from?,
+            nonce: self.nonce?,
+            hashes: self.hashes.clone(),
+            submitted_block: self.submitted_block.unwrap_or_default(),
+        })
+    }
+}
+
+/// Durable storage for withdrawal records.
+///
+/// `insert` and `update` must be atomic: they are what guarantees a key is
+/// broadcast at most once, also across processes sharing the store.
+pub trait WithdrawalStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<WithdrawalRecord>>;
+    /// Store a new record; false when the key already exists
+    fn insert(&self, record: &WithdrawalRecord) -> Result<bool>;
+    /// Replace the record only if its stored status is still `expected`
+    fn update(&self, expected: WithdrawalStatus, record: &WithdrawalRecord) -> Result<bool>;
+    fn list(&self, status: Option<WithdrawalStatus>) -> Result<Vec<WithdrawalRecord>>;
+}
+
+/// In-memory store for tests and single-run tools
+#[derive(Default)]
+pub struct MemoryWithdrawalStore {
+    records: Mutex<HashMap<String, WithdrawalRecord>>,
+}
+
+impl WithdrawalStore for MemoryWithdrawalStore {
+    fn get(&self, key: &str) -> Result<Option<WithdrawalRecord>> {
+        Ok(lock(&self.records)?.get(key).cloned())
+    }
+
+    fn insert(&self, record: &WithdrawalRecord) -> Result<bool> {
+        insert_record(&mut *lock(&self.records)?, record)
+    }
+
+    fn update(&self, expected: WithdrawalStatus, record: &WithdrawalRecord) -> Result<bool> {
+        update_record(&mut *lock(&self.records)?, expected, record)
+    }
+
+    fn list(&self, status: Option<WithdrawalStatus>) -> Result<Vec<WithdrawalRecord>> {
+        Ok(filter_records(&*lock(&self.records)?, status))
+    }
+}
+
+/// JSON file keyed by idempotency key, replaced atomically on every change.
+///
+/// Every operation locks `<path>.lock` (exclusively for writes) and re-reads
+/// the file, so several processes can share one store.
+pub struct FileWithdrawalStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+}
+
+impl FileWithdrawalStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let store = Self { lock_path: path.with_extension("lock"), path };
+
+        // Fail early on an unreadable or corrupt file
+        let _lock = store.lock_file(false)?;
+        store.load()?;
+
+        Ok(store)
+    }
+
+    // Held until the returned file is dropped
+    fn lock_file(&self, exclusive: bool) -> Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&self.lock_path)
+            .with_context(|| format!("Cannot open lock file {:?}", self.lock_path))?;
+
+        if exclusive {
+            file.lock()?;
+        } else {
+            file.lock_shared()?;
+        }
+
+        Ok(file)
+    }
+
+    fn load(&self) -> Result<HashMap<String, WithdrawalRecord>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)
+                .with_context(|| format!("Corrupt withdrawal file {:?}", self.path))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, records: &HashMap<String, WithdrawalRecord>) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(records)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl WithdrawalStore for FileWithdrawalStore {
+    fn get(&self, key: &str) -> Result<Option<WithdrawalRecord>> {
+        let _lock = self.lock_file(false)?;
+        Ok(self.load()?.remove(key))
+    }
+
+    fn insert(&self, record: &WithdrawalRecord) -> Result<bool> {
+        let _lock = self.lock_file(true)?;
+        let mut records = self.load()?;
+        let inserted = insert_record(&mut records, record)?;
+        if inserted {
+            self.save(&records)?;
+        }
+        Ok(inserted)
+    }
+
+    fn update(&self, expected: WithdrawalStatus, record: &WithdrawalRecord) -> Result<bool> {
+        let _lock = self.lock_file(true)?;
+        let mut records = self.load()?;
+        let updated = update_record(&mut records, expected, record)?;
+        if updated {
+            self.save(&records)?;
+        }
+        Ok(updated)
+    }
+
+    fn list(&self, status: Option<WithdrawalStatus>) -> Result<Vec<WithdrawalRecord>> {
+        let _lock = self.lock_file(false)?;
+        Ok(filter_records(&self.load()?, status))
+    }
+}
+
+/// Payouts on top of `TokenManager` where each idempotency key is
+/// broadcast at most once, however often it is submitted or processed.
+pub struct WithdrawalQueue<S> {
+    token: Arc<TokenManager>,
+    store: S,
+}
+
+impl<S: WithdrawalStore> WithdrawalQueue<S> {
+    pub fn new(token: Arc<TokenManager>, store: S) -> Self {
+        Self { token, store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Queue a payout. Re-submitting a key returns the existing record;
+    /// reusing it for a different recipient or amount is an error.
+    pub fn submit(&self, key: &str, to: Address, amount: U256) -> Result<WithdrawalRecord> {
+        let record = WithdrawalRecord::new(key, to, amount);
+
+        if self.store.insert(&record)? {
+            log::debug!("withdrawal {key} queued");
+            return Ok(record);
+        }
+
+        let existing = self.get(key)?;
+        if existing.to != to || existing.amount != amount {
+            anyhow::bail!("Idempotency key {key} was already used for a different payout");
+        }
+
+        Ok(existing)
+    }
+
+    pub fn get(&self, key: &str) -> Result<WithdrawalRecord> {
+        self.store
+            .get(key)?
+            .ok_or_else(|| anyhow::anyhow!("Unknown withdrawal {key}"))
+    }
+
+    /// Broadcast a queued payout. The signed transaction is stored before
+    /// it is sent; a payout left `Signed` by a failed send is refreshed and,
+    /// if still pending, rebroadcast with the same bytes. A claim that was
+    /// never signed goes back to `Queued` after `CLAIM_TIMEOUT_SECS`. Any
+    /// other status is returned unchanged, so calling this again after a
+    /// timeout is always safe.
+    pub async fn process(&self, key: &str) -> Result<WithdrawalRecord> {
+        let mut record = self.get(key)?;
+
+        match (record.status, &record.raw_tx) {
+            (WithdrawalStatus::Queued, _) => {}
+            (WithdrawalStatus::Signed, None) if now().saturating_sub(record.updated_at) >= CLAIM_TIMEOUT_SECS => {
+                log::warn!("withdrawal {key} was claimed but never signed; requeueing");
+                record.status = WithdrawalStatus::Queued;
+                self.transition(WithdrawalStatus::Signed, &mut record)?;
+            }
+            (WithdrawalStatus::Signed, Some(raw)) => {
+                let raw = raw.clone();
+
+                // An earlier send may have reached the network after all
+                let record = self.refresh(key).await?;
+                if record.status != WithdrawalStatus::Signed {
+                    return Ok(record);
+                }
+
+                return self.send_signed(record, &raw).await;
+            }
+            _ => return Ok(record),
+        }
+
+        // Claim the key before anything is signed
+        record.status = WithdrawalStatus::Signed;
+        record.updated_at = now();
+        if !self.store.update(WithdrawalStatus::Queued, &record)? {
+            return self.get(key);
+        }
+
+        let signed = async {
+            let prepared = self.token.prepare_transfer(record.to, record.amount).await?;
+            self.token.sign_transfer(record.to, record.amount, &prepared).await
+        };
+
+        let signed = match signed.await {
+            Ok(signed) => signed,
+            Err(e) => {
+                // Nothing reached the network and any nonce was released,
+                // so the payout can be retried
+                record.status = WithdrawalStatus::Queued;
+                record.error = Some(e.to_string());
+                self.transition(WithdrawalStatus::Signed, &mut record)?;
+                return Err(e);
+            }
+        };
+
+        // Write ahead: from here on the exact transaction is recoverable
+        record.hashes.push(signed.hash);
+        record.from = Some(signed.from);
+        record.nonce = Some(signed.nonce);
+        record.raw_tx = Some(signed.raw.clone());
+        self.transition(WithdrawalStatus::Signed, &mut record)?;
+
+        self.send_signed(record, &signed.raw).await
+    }
+
+    /// Check a pending payout for inclusion and record the result.
+    ///
+    /// Covers `Signed` payouts with a stored transaction too, whose send may
+    /// have succeeded without us hearing back. A payout only becomes
+    /// `Failed` once its nonce is known to be used by another transaction;
+    /// lookup errors are returned and leave the record unchanged.
+    pub async fn refresh(&self, key: &str) -> Result<WithdrawalRecord> {
+        let mut record = self.get(key)?;
+        let expected = record.status;
+
+        let pending = match expected {
+            WithdrawalStatus::Broadcast | WithdrawalStatus::Replaced => true,
+            WithdrawalStatus::Signed => record.raw_tx.is_some(),
+            _ => false,
+        };
+        if !pending {
+            return Ok(record);
+        }
+
+        let Some(group) = record.replacement_group() else {
+            return Ok(record);
+        };
+
+        match self.token.find_mined(&group).await {
+            Ok(Some((hash, receipt))) => {
+                record.mined_hash = Some(hash);
+                if receipt.status() {
+                    record.status = WithdrawalStatus::Mined;
+                    record.received = Some(self.token.received_in_receipt(&receipt, record.to));
+                } else {
+                    record.status = WithdrawalStatus::Failed;
+                    record.error = Some(match self.token.transaction_failure(&receipt).await {
+                        Ok(failure) => failure.to_string(),
+                        Err(_) => format!("Transaction {hash} reverted"),
+                    });
+                }
+            }
+            Ok(None) => return Ok(record),
+            Err(e) => match e.downcast_ref::<NonceConsumed>() {
+                // None of our hashes can be mined anymore
+                Some(consumed) => {
+                    record.status = WithdrawalStatus::Failed;
+                    record.error = Some(consumed.to_string());
+                }
+                None => return Err(e),
+            },
+        }
+
+        self.transition(expected, &mut record)?;
+        Ok(record)
+    }
+
+    /// Replace a stuck payout with a higher fee.
+    ///
+    /// The replacement is stored before it is sent, so `refresh` tracks it
+    /// even if the send fails after reaching the network.
+    pub async fn speed_up(&self, key: &str, bump_percent: u64) -> Result<WithdrawalRecord> {
+        let mut record = self.get(key)?;
+        let expected = record.status;
+
+        let group = match (expected, record.replacement_group()) {
+            (WithdrawalStatus::Broadcast | WithdrawalStatus::Replaced, Some(group)) => group,
+            _ => anyhow::bail!("Withdrawal {key} is not pending ({expected:?})"),
+        };
+
+        let signed = self.token.sign_speed_up(&group, bump_percent).await?;
+
+        record.hashes.push(signed.hash);
+        record.raw_tx = Some(signed.raw.clone());
+        record.status = WithdrawalStatus::Replaced;
+        self.transition(expected, &mut record)?;
+
+        if let Err(e) = self.token.submit_raw(&signed.raw).await {
+            record.error = Some(e.to_string());
+            self.transition(WithdrawalStatus::Replaced, &mut record)?;
+            return Err(e);
+        }
+
+        Ok(record)
+    }
+
+    /// Refresh every broadcast or replaced payout, and every signed one
+    /// whose send may have gone through
+    pub async fn refresh_pending(&self) -> Result<Vec<WithdrawalRecord>> {
+        let mut pending = self.store.list(Some(WithdrawalStatus::Broadcast))?;
+        pending.extend(self.store.list(Some(WithdrawalStatus::Replaced))?);
+        pending.extend(
+            self.store
+                .list(Some(WithdrawalStatus::Signed))?
+                .into_iter()
+                .filter(|record| record.raw_tx.is_some()),
+        );
+
+        let mut refreshed = Vec::with_capacity(pending.len());
+        for record in pending {
+            refreshed.push(self.refresh(&record.key).await?);
+        }
+
+        Ok(refreshed)
+    }
+
+    async fn send_signed(&self, mut record: WithdrawalRecord, raw: &[u8]) -> Result<WithdrawalRecord> {
+        match self.token.submit_raw(raw).await {
+            Ok(sent) => {
+                record.submitted_block = Some(sent.submitted_block);
+                record.status = WithdrawalStatus::Broadcast;
+                record.error = None;
+                self.transition(WithdrawalStatus::Signed, &mut record)?;
+                Ok(record)
+            }
+            Err(e) => {
+                // Stays `Signed`: the next `process` resends the same bytes
+                record.error = Some(e.to_string());
+                self.transition(WithdrawalStatus::Signed, &mut record)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn transition(&self, expected: WithdrawalStatus, record: &mut WithdrawalRecord) -> Result<()> {
+        record.updated_at = now();
+
+        if !self.store.update(expected, record)? {
+            anyhow::bail!("Withdrawal {} changed concurrently (expected {expected:?})", record.key);
+        }
+
+        log::debug!("withdrawal {} -> {:?}", record.key, record.status);
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> Result<std::sync::MutexGuard<'_, T>> {
+    mutex.lock().map_err(|_| anyhow::anyhow!("Withdrawal store lock poisoned"))
+}
+
+fn insert_record(records: &mut HashMap<String, WithdrawalRecord>, record: &WithdrawalRecord) -> Result<bool> {
+    if records.contains_key(&record.key) {
+        return Ok(false);
+    }
+    records.insert(record.key.clone(), record.clone());
+    Ok(true)
+}
+
+fn update_record(
+    records: &mut HashMap<String, WithdrawalRecord>,
+    expected: WithdrawalStatus,
+    record: &WithdrawalRecord,
+) -> Result<bool> {
+    match records.get_mut(&record.key) {
+        Some(stored) if stored.status == expected => {
+            *stored = record.clone();
+            Ok(true)
+        }
+        Some(_) => Ok(false),
+        None => anyhow::bail!("Unknown withdrawal {}", record.key),
+    }
+}
+
+fn filter_records(records: &HashMap<String, WithdrawalRecord>, status: Option<WithdrawalStatus>) -> Vec<WithdrawalRecord> {
+    records
+        .values()
+        .filter(|r| status.is_none_or(|s| r.status == s))
+        .cloned()
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::network::EthereumWallet;
+    use alloy::providers::ProviderBuilder;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::sol_types::SolValue;
+    use alloy::transports::mock::Asserter;
+
+    use super::*;
+
+    // Queue whose RPC has answered token metadata and nothing else
+    async fn queue(asserter: &Asserter) -> WithdrawalQueue<MemoryWithdrawalStore> {
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(PrivateKeySigner::random()))
+            .connect_mocked_client(asserter.clone());
+
+        asserter.push_success(&Bytes::from(U256::from(18u8).abi_encode()));
+        asserter.push_success(&Bytes::from("Token".to_string().abi_encode()));
+        asserter.push_success(&Bytes::from("TKN".to_string().abi_encode()));
+        let token = TokenManager::new(Arc::new(provider), Address::repeat_byte(0x55)).await.unwrap();
+
+        WithdrawalQueue::new(Arc::new(token), MemoryWithdrawalStore::default())
+    }
+
+    #[tokio::test]
+    async fn failure_before_sending_requeues() {
+        let asserter = Asserter::new();
+        let queue = queue(&asserter).await;
+        queue.submit("w1", Address::repeat_byte(0x11), U256::from(5u64)).unwrap();
+
+        asserter.push_failure_msg("connection reset");
+        assert!(queue.process("w1").await.is_err());
+
+        let record = queue.get("w1").unwrap();
+        assert_eq!(record.status, WithdrawalStatus::Queued);
+        assert!(record.error.is_some());
+        assert!(record.hashes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stale_unsigned_claim_is_requeued() {
+        let asserter = Asserter::new();
+        let queue = queue(&asserter).await;
+        let mut record = queue.submit("w1", Address::repeat_byte(0x11), U256::from(5u64)).unwrap();
+
+        // A process claimed the key and died before signing
+        record.status = WithdrawalStatus::Signed;
+        record.updated_at = now() - CLAIM_TIMEOUT_SECS;
+        assert!(queue.store().update(WithdrawalStatus::Queued, &record).unwrap());
+
+        asserter.push_failure_msg("connection reset");
+        assert!(queue.process("w1").await.is_err());
+        assert_eq!(queue.get("w1").unwrap().status, WithdrawalStatus::Queued);
+
+        // A fresh claim belongs to a live process and is left alone
+        record.updated_at = now();
+        assert!(queue.store().update(WithdrawalStatus::Queued, &record).unwrap());
+        assert_eq!(queue.process("w1").await.unwrap().status, WithdrawalStatus::Signed);
+    }
+}